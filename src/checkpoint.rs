@@ -0,0 +1,156 @@
+//! Saving searches to disk so that long runs survive being stopped
+//!
+//! A checkpoint starts with a header giving the format version, the kind of search and the size
+//! of its player sets, followed by the search itself encoded with bincode. A resumed search
+//! continues exactly as the saved one would have.
+
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{DF2Solutions, PlayerSet, State, StateSolutions, DF2};
+
+/// Bumped whenever the way a search is stored changes
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+const MAGIC: [u8; 8] = *b"BGSCHED\0";
+
+#[derive(Debug, Error)]
+pub enum CheckpointError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Encoding(#[from] bincode::Error),
+    #[error("Not a checkpoint file")]
+    NotACheckpoint,
+    #[error("Checkpoint version {found} is not supported, only version {supported} is")]
+    UnsupportedVersion { found: u32, supported: u32 },
+    #[error("The checkpoint holds a {found} search but a {expected} search was expected")]
+    WrongSearch {
+        found: String,
+        expected: &'static str,
+    },
+    #[error("The checkpoint holds sets of {found} players but sets of {expected} were expected")]
+    WrongPlayerSet { found: u32, expected: u32 },
+    #[error("The search in the checkpoint does not match its config")]
+    Inconsistent,
+}
+
+/// A search which can be written to a checkpoint and resumed from it
+pub trait Checkpoint: Serialize + DeserializeOwned {
+    /// Stored in the checkpoint so it is not resumed as a different kind of search
+    const NAME: &'static str;
+    /// Capacity of the player sets, which must match when resuming
+    const PLAYER_SET_CAPACITY: usize;
+
+    /// Whether the search is consistent with its config, as checked when resuming
+    fn fits_config(&self) -> bool;
+
+    fn write_checkpoint(&self, output: &mut dyn Write) -> Result<(), CheckpointError> {
+        output.write_all(&MAGIC)?;
+        bincode::serialize_into(&mut *output, &CHECKPOINT_VERSION)?;
+        bincode::serialize_into(
+            &mut *output,
+            &(Self::NAME, Self::PLAYER_SET_CAPACITY as u32),
+        )?;
+        bincode::serialize_into(&mut *output, self)?;
+        Ok(())
+    }
+
+    fn read_checkpoint(input: &mut dyn Read) -> Result<Self, CheckpointError> {
+        let mut magic = [0; MAGIC.len()];
+        match input.read_exact(&mut magic) {
+            Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return Err(CheckpointError::NotACheckpoint)
+            }
+            result => result?,
+        }
+        if magic != MAGIC {
+            return Err(CheckpointError::NotACheckpoint);
+        }
+        let version: u32 = bincode::deserialize_from(&mut *input)?;
+        if version != CHECKPOINT_VERSION {
+            return Err(CheckpointError::UnsupportedVersion {
+                found: version,
+                supported: CHECKPOINT_VERSION,
+            });
+        }
+        let (name, capacity): (String, u32) = bincode::deserialize_from(&mut *input)?;
+        if name != Self::NAME {
+            return Err(CheckpointError::WrongSearch {
+                found: name,
+                expected: Self::NAME,
+            });
+        }
+        if capacity as usize != Self::PLAYER_SET_CAPACITY {
+            return Err(CheckpointError::WrongPlayerSet {
+                found: capacity,
+                expected: Self::PLAYER_SET_CAPACITY as u32,
+            });
+        }
+        let search: Self = bincode::deserialize_from(&mut *input)?;
+        if !search.fits_config() {
+            return Err(CheckpointError::Inconsistent);
+        }
+        Ok(search)
+    }
+
+    /// Writes to a file next to `path` then renames it over `path`, so a crash while saving
+    /// leaves the previous checkpoint in place
+    fn save_checkpoint(&self, path: &Path) -> Result<(), CheckpointError> {
+        let mut partial = OsString::from(path.as_os_str());
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        let mut output = BufWriter::new(File::create(&partial)?);
+        self.write_checkpoint(&mut output)?;
+        let file = output.into_inner().map_err(|error| error.into_error())?;
+        file.sync_all()?;
+        std::fs::rename(&partial, path)?;
+        Ok(())
+    }
+
+    fn load_checkpoint(path: &Path) -> Result<Self, CheckpointError> {
+        Self::read_checkpoint(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+impl<S: PlayerSet> Checkpoint for DF2<S> {
+    const NAME: &'static str = "DF2";
+    const PLAYER_SET_CAPACITY: usize = S::CAPACITY;
+
+    fn fits_config(&self) -> bool {
+        DF2::fits_config(self)
+    }
+}
+
+impl<S: PlayerSet> Checkpoint for DF2Solutions<S> {
+    const NAME: &'static str = "DF2Solutions";
+    const PLAYER_SET_CAPACITY: usize = S::CAPACITY;
+
+    fn fits_config(&self) -> bool {
+        DF2Solutions::fits_config(self)
+    }
+}
+
+impl<S: PlayerSet> Checkpoint for State<S> {
+    const NAME: &'static str = "State";
+    const PLAYER_SET_CAPACITY: usize = S::CAPACITY;
+
+    fn fits_config(&self) -> bool {
+        State::fits_config(self)
+    }
+}
+
+impl<S: PlayerSet> Checkpoint for StateSolutions<S> {
+    const NAME: &'static str = "StateSolutions";
+    const PLAYER_SET_CAPACITY: usize = S::CAPACITY;
+
+    fn fits_config(&self) -> bool {
+        StateSolutions::fits_config(self)
+    }
+}
@@ -0,0 +1,527 @@
+use std::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ConfigError, OutOfRange, PinError, PlayerSet, Roster};
+
+/// Players, rounds and tables are stored as `u8`, with the top player number kept as the empty
+/// seat marker
+pub(crate) const MAX_COUNT: usize = u8::MAX as usize;
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "ConfigData")]
+pub struct Config {
+    /// Number of seats at each table, indexed by round then table
+    seats: Vec<Vec<usize>>,
+    /// At least the number of seats in a round, with the extra players sitting out
+    player_count: usize,
+    /// Players who cannot attend, indexed by round, in ascending order
+    absent: Vec<Vec<usize>>,
+    /// Players fixed to a table before solving, indexed by round then table
+    pins: Vec<Vec<Vec<usize>>>,
+    /// Pairs of players who may never share a table, lowest player first
+    forbidden_pairs: Vec<(usize, usize)>,
+    /// Pairs of players who must share a table at least once, lowest player first
+    must_meet_pairs: Vec<(usize, usize)>,
+}
+
+/// A `Config` as read, before it is checked
+#[derive(Deserialize)]
+struct ConfigData {
+    seats: Vec<Vec<usize>>,
+    player_count: usize,
+    absent: Vec<Vec<usize>>,
+    pins: Vec<Vec<Vec<usize>>>,
+    forbidden_pairs: Vec<(usize, usize)>,
+    must_meet_pairs: Vec<(usize, usize)>,
+}
+
+impl TryFrom<ConfigData> for Config {
+    type Error = ConfigError;
+
+    /// Rebuilds the config with the builders, so a corrupted one is rejected as it would have
+    /// been when made
+    fn try_from(data: ConfigData) -> Result<Self, ConfigError> {
+        let ConfigData {
+            seats,
+            player_count,
+            absent,
+            pins,
+            forbidden_pairs,
+            must_meet_pairs,
+        } = data;
+        let mut config = Self::with_round_table_sizes(seats)?.with_player_count(player_count)?;
+        for round in 0..absent.len().max(pins.len()) {
+            config.round(round)?;
+        }
+        let mut absent_players: Vec<usize> = absent.iter().flatten().copied().collect();
+        absent_players.sort_unstable();
+        absent_players.dedup();
+        for player in absent_players {
+            let rounds: Vec<usize> = (0..config.round_count())
+                .filter(|&round| {
+                    !absent
+                        .get(round)
+                        .is_some_and(|absent| absent.contains(&player))
+                })
+                .collect();
+            config = config.with_available_rounds(player, &rounds)?;
+        }
+        for (round, tables) in pins.iter().enumerate() {
+            for (table, players) in tables.iter().enumerate() {
+                for &player in players.iter() {
+                    config = config.with_pin(player, round, table)?;
+                }
+            }
+        }
+        for (first, second) in forbidden_pairs {
+            config = config.with_forbidden_pair(first, second)?;
+        }
+        for (first, second) in must_meet_pairs {
+            config = config.with_must_meet_pair(first, second)?;
+        }
+        Ok(config)
+    }
+}
+
+impl Config {
+    /// Every table seats the same number of players in every round
+    pub fn new(
+        round_count: usize,
+        table_count: usize,
+        players_per_table: usize,
+    ) -> Result<Self, ConfigError> {
+        Self::with_table_sizes(round_count, vec![players_per_table; table_count])
+    }
+
+    /// Tables may seat different numbers of players, but each keeps its size in every round
+    pub fn with_table_sizes(
+        round_count: usize,
+        table_sizes: Vec<usize>,
+    ) -> Result<Self, ConfigError> {
+        Self::with_round_table_sizes(vec![table_sizes; round_count])
+    }
+
+    /// Table sizes indexed by round then table. Every round must have the same number of
+    /// tables and the same total number of seats
+    pub fn with_round_table_sizes(seats: Vec<Vec<usize>>) -> Result<Self, ConfigError> {
+        let first_round = seats.first().ok_or(ConfigError::Empty)?;
+        let table_count = first_round.len();
+        let player_count = first_round.iter().sum();
+        if table_count == 0 || seats.iter().flatten().any(|&size| size == 0) {
+            return Err(ConfigError::Empty);
+        }
+        if seats.len() > MAX_COUNT || table_count > MAX_COUNT {
+            return Err(ConfigError::TooManyRoundsOrTables { max: MAX_COUNT });
+        }
+        if player_count > MAX_COUNT {
+            return Err(ConfigError::TooManyPlayers {
+                player_count,
+                max: MAX_COUNT,
+            });
+        }
+        for (round, sizes) in seats.iter().enumerate() {
+            if sizes.len() != table_count {
+                return Err(ConfigError::UnevenTableCount {
+                    round,
+                    table_count: sizes.len(),
+                    expected: table_count,
+                });
+            }
+            let seat_count = sizes.iter().sum();
+            if seat_count != player_count {
+                return Err(ConfigError::UnevenSeatCount {
+                    round,
+                    seat_count,
+                    expected: player_count,
+                });
+            }
+        }
+        Ok(Self {
+            absent: vec![Vec::new(); seats.len()],
+            pins: vec![vec![Vec::new(); table_count]; seats.len()],
+            forbidden_pairs: Vec::new(),
+            must_meet_pairs: Vec::new(),
+            seats,
+            player_count,
+        })
+    }
+
+    /// Allows more players than there are seats in a round, with players taking turns to sit
+    /// out. Sit-outs are spread as evenly as possible: nobody sits out twice before everyone
+    /// has sat out once
+    ///
+    /// For example 22 players at 5 tables of 4 has 2 players sitting out each round
+    pub fn with_player_count(mut self, player_count: usize) -> Result<Self, ConfigError> {
+        if player_count < self.seat_count() {
+            return Err(ConfigError::TooFewPlayers {
+                player_count,
+                seat_count: self.seat_count(),
+            });
+        }
+        if player_count > MAX_COUNT {
+            return Err(ConfigError::TooManyPlayers {
+                player_count,
+                max: MAX_COUNT,
+            });
+        }
+        self.player_count = player_count;
+        for absent in self.absent.iter_mut() {
+            absent.retain(|&player| player < player_count);
+        }
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Sets the player count to the number of players on the roster, whose names can then be
+    /// turned into player numbers with `Roster::id`
+    pub fn with_roster(self, roster: &Roster) -> Result<Self, ConfigError> {
+        self.with_player_count(roster.len())
+    }
+
+    /// Limits a player to the listed rounds, for players who arrive late or leave early. Rounds
+    /// they miss count as sitting out, so they are not picked to sit out again until everyone
+    /// else has caught up
+    pub fn with_available_rounds(
+        mut self,
+        player: usize,
+        rounds: &[usize],
+    ) -> Result<Self, ConfigError> {
+        if player >= self.player_count {
+            return Err(OutOfRange {
+                index: player,
+                count: self.player_count,
+            }
+            .into());
+        }
+        for &round in rounds.iter() {
+            self.round(round)?;
+        }
+        for (round, absent) in self.absent.iter_mut().enumerate() {
+            absent.retain(|&other| other != player);
+            if !rounds.contains(&round) {
+                absent.push(player);
+                absent.sort_unstable();
+            }
+        }
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Fixes a player to a table in a round, with the solvers filling the remaining seats
+    pub fn with_pin(
+        mut self,
+        player: usize,
+        round: usize,
+        table: usize,
+    ) -> Result<Self, ConfigError> {
+        let round = self.round(round)?;
+        let table = self.table(table)?;
+        self.pins[round.index()][table.index()].push(player);
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Stops two players from ever sharing a table
+    pub fn with_forbidden_pair(mut self, first: usize, second: usize) -> Result<Self, ConfigError> {
+        let pair = (first.min(second), first.max(second));
+        if !self.forbidden_pairs.contains(&pair) {
+            self.forbidden_pairs.push(pair);
+        }
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Requires two players to share a table in at least one round
+    pub fn with_must_meet_pair(mut self, first: usize, second: usize) -> Result<Self, ConfigError> {
+        let pair = (first.min(second), first.max(second));
+        if !self.must_meet_pairs.contains(&pair) {
+            self.must_meet_pairs.push(pair);
+        }
+        self.validate()?;
+        Ok(self)
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        for (round, absent) in self.absent.iter().enumerate() {
+            let available = self.player_count - absent.len();
+            if available < self.seat_count() {
+                return Err(ConfigError::TooFewAvailable {
+                    round,
+                    available,
+                    seat_count: self.seat_count(),
+                });
+            }
+        }
+        for &(first, second) in self
+            .forbidden_pairs
+            .iter()
+            .chain(self.must_meet_pairs.iter())
+        {
+            if second >= self.player_count {
+                return Err(OutOfRange {
+                    index: second,
+                    count: self.player_count,
+                }
+                .into());
+            }
+            if first == second {
+                return Err(ConfigError::PairWithSelf { player: first });
+            }
+        }
+        if let Some(&(first, second)) = self
+            .forbidden_pairs
+            .iter()
+            .find(|pair| self.must_meet_pairs.contains(pair))
+        {
+            return Err(ConfigError::ConflictingPair { first, second });
+        }
+        self.validate_pins()?;
+        Ok(())
+    }
+
+    fn validate_pins(&self) -> Result<(), PinError> {
+        let mut in_round = std::collections::HashSet::new();
+        let mut on_table = std::collections::HashSet::new();
+        let mut met = std::collections::HashSet::new();
+        for round in self.rounds() {
+            for table in self.tables() {
+                let pinned = self.pinned(round, table);
+                if pinned.len() > self.seats(round, table) {
+                    return Err(PinError::TableOverfull {
+                        round: round.index(),
+                        table: table.index(),
+                    });
+                }
+                for &player in pinned.iter() {
+                    if player >= self.player_count {
+                        return Err(PinError::UnknownPlayer {
+                            player,
+                            player_count: self.player_count,
+                        });
+                    }
+                    if !self.is_available(player, round) {
+                        return Err(PinError::Unavailable {
+                            player,
+                            round: round.index(),
+                        });
+                    }
+                    if !in_round.insert((player, round)) {
+                        return Err(PinError::TwiceInRound {
+                            player,
+                            round: round.index(),
+                        });
+                    }
+                    if !on_table.insert((player, table)) {
+                        return Err(PinError::TableRevisited {
+                            player,
+                            table: table.index(),
+                        });
+                    }
+                    for &other in pinned.iter().filter(|&&other| other > player) {
+                        if self.forbidden_pairs.contains(&(player, other)) {
+                            return Err(PinError::ForbiddenPair {
+                                first: player,
+                                second: other,
+                            });
+                        }
+                        if !met.insert((player, other)) {
+                            return Err(PinError::MeetTwice {
+                                first: player,
+                                second: other,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn round_count(&self) -> usize {
+        self.seats.len()
+    }
+
+    pub fn table_count(&self) -> usize {
+        self.seats[0].len()
+    }
+
+    /// Number of players seated at a table
+    pub fn seats(&self, round: Round, table: Table) -> usize {
+        self.seats[round.index()][table.index()]
+    }
+
+    /// Number of seats at every table, if they are all the same size
+    pub fn uniform_table_size(&self) -> Option<usize> {
+        let size = self.seats[0][0];
+        if self.seats.iter().flatten().all(|&other| other == size) {
+            Some(size)
+        } else {
+            None
+        }
+    }
+
+    /// Size of the largest table in the round
+    pub fn max_seats(&self, round: Round) -> usize {
+        self.seats[round.index()].iter().copied().max().unwrap()
+    }
+
+    pub const fn player_count(&self) -> usize {
+        self.player_count
+    }
+
+    /// Number of seats in each round
+    pub fn seat_count(&self) -> usize {
+        self.seats[0].iter().sum()
+    }
+
+    /// Number of available players sitting out a round
+    pub fn sit_out_count(&self, round: Round) -> usize {
+        self.player_count - self.absent[round.index()].len() - self.seat_count()
+    }
+
+    pub(crate) fn has_sit_outs(&self) -> bool {
+        self.rounds().any(|round| self.sit_out_count(round) > 0)
+    }
+
+    pub fn is_available(&self, player: usize, round: Round) -> bool {
+        player < self.player_count && self.absent[round.index()].binary_search(&player).is_err()
+    }
+
+    /// Whether any player misses a round
+    pub(crate) fn has_absences(&self) -> bool {
+        self.absent.iter().any(|absent| !absent.is_empty())
+    }
+
+    /// Players pinned to a table, who take its first seats
+    pub fn pinned(&self, round: Round, table: Table) -> &[usize] {
+        &self.pins[round.index()][table.index()]
+    }
+
+    pub(crate) fn has_pins(&self) -> bool {
+        self.pins.iter().flatten().any(|pinned| !pinned.is_empty())
+    }
+
+    pub fn forbidden_pairs(&self) -> &[(usize, usize)] {
+        &self.forbidden_pairs
+    }
+
+    pub fn must_meet_pairs(&self) -> &[(usize, usize)] {
+        &self.must_meet_pairs
+    }
+
+    /// Players who may never share a table with `player`
+    pub(crate) fn forbidden_with<S: PlayerSet>(&self, player: usize) -> S {
+        let mut forbidden = S::empty();
+        for &(first, second) in self.forbidden_pairs.iter() {
+            if first == player {
+                forbidden.insert(second);
+            } else if second == player {
+                forbidden.insert(first);
+            }
+        }
+        forbidden
+    }
+
+    /// Whether players can be relabelled freely, so that round 0 can be filled in order
+    pub(crate) fn players_interchangeable(&self) -> bool {
+        !self.has_absences()
+            && !self.has_pins()
+            && self.forbidden_pairs.is_empty()
+            && self.must_meet_pairs.is_empty()
+    }
+
+    /// Whether rounds can be reordered freely, which also needs everyone to play every round
+    pub(crate) fn rounds_interchangeable(&self) -> bool {
+        self.players_interchangeable() && !self.has_sit_outs()
+    }
+
+    pub(crate) fn available<S: PlayerSet>(&self, round: Round) -> S {
+        let mut available = S::first_n(self.player_count);
+        for &player in self.absent[round.index()].iter() {
+            available.remove(player);
+        }
+        available
+    }
+
+    /// Checks that every player fits in the player set used by a solver
+    pub(crate) fn check_capacity<S: PlayerSet>(&self) -> Result<(), ConfigError> {
+        if self.player_count() > S::CAPACITY {
+            Err(ConfigError::TooManyPlayers {
+                player_count: self.player_count(),
+                max: S::CAPACITY,
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn round(&self, index: usize) -> Result<Round, OutOfRange> {
+        if index < self.round_count() {
+            Ok(Round(index as u8))
+        } else {
+            Err(OutOfRange {
+                index,
+                count: self.round_count(),
+            })
+        }
+    }
+
+    pub fn table(&self, index: usize) -> Result<Table, OutOfRange> {
+        if index < self.table_count() {
+            Ok(Table(index as u8))
+        } else {
+            Err(OutOfRange {
+                index,
+                count: self.table_count(),
+            })
+        }
+    }
+
+    pub fn rounds(&self) -> impl Iterator<Item = Round> {
+        (0..self.round_count() as u8).map(Round)
+    }
+
+    pub fn tables(&self) -> impl Iterator<Item = Table> {
+        (0..self.table_count() as u8).map(Table)
+    }
+
+    pub(crate) fn last_table(&self) -> Table {
+        Table(self.table_count() as u8 - 1)
+    }
+}
+
+impl Default for Config {
+    /// 6 rounds of 6 tables with 4 players each
+    fn default() -> Self {
+        Self::new(6, 6, 4).unwrap()
+    }
+}
+
+/// Index of a round, in range of the config it came from when made with `Config::round`. Indices
+/// read with serde are not checked on their own, so searches holding them check them against
+/// their config when read from a checkpoint
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct Round(u8);
+
+impl Round {
+    pub const ZERO: Self = Self(0);
+
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Index of a table, in range of the config it came from when made with `Config::table`. Indices
+/// read with serde are not checked on their own, so searches holding them check them against
+/// their config when read from a checkpoint
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+pub struct Table(u8);
+
+impl Table {
+    pub const ZERO: Self = Self(0);
+
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
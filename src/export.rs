@@ -0,0 +1,247 @@
+//! Writing schedules out for spreadsheets, web pages and other programs
+//!
+//! Machine readable formats number rounds, tables, seats and players from 0, matching
+//! `Schedule`. Markdown and the grid are meant to be read, so number rounds and tables from 1.
+//! Every exporter can be given a roster to show names alongside or instead of player numbers.
+
+use std::io::Write;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{Player, Roster, Schedule, ScheduleGrid};
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// Writes a schedule in some format
+pub trait Exporter {
+    fn export(&self, schedule: &Schedule, output: &mut dyn Write) -> Result<(), ExportError>;
+
+    /// Exports to a string, for formats which are always text
+    fn export_string(&self, schedule: &Schedule) -> Result<String, ExportError> {
+        let mut output = Vec::new();
+        self.export(schedule, &mut output)?;
+        Ok(String::from_utf8(output).expect("exporters write UTF-8"))
+    }
+}
+
+/// Name of the player if there is a roster, otherwise their number
+pub(crate) fn label(roster: Option<&Roster>, player: usize) -> String {
+    match roster {
+        Some(roster) => roster.label(player),
+        None => player.to_string(),
+    }
+}
+
+fn table_count(rounds: &ScheduleGrid) -> usize {
+    rounds.iter().map(Vec::len).max().unwrap_or(0)
+}
+
+/// One `round,table,seat,player` line per seat, after a header line, with a `name` column added
+/// when there is a roster
+#[derive(Clone, Debug, Default)]
+pub struct CsvExporter {
+    pub roster: Option<Roster>,
+}
+
+/// Quotes a CSV cell if it would otherwise be misread
+pub(crate) fn csv_cell(text: &str) -> String {
+    if text.contains([',', '"', '\n']) || text.trim() != text {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+impl Exporter for CsvExporter {
+    fn export(&self, schedule: &Schedule, output: &mut dyn Write) -> Result<(), ExportError> {
+        write!(output, "round,table,seat,player")?;
+        if self.roster.is_some() {
+            write!(output, ",name")?;
+        }
+        writeln!(output)?;
+        for (round, tables) in schedule.rounds().iter().enumerate() {
+            for (table, players) in tables.iter().enumerate() {
+                for (seat, &player) in players.iter().enumerate() {
+                    write!(output, "{},{},{},{}", round, table, seat, player)?;
+                    if let Some(roster) = self.roster.as_ref() {
+                        write!(output, ",{}", csv_cell(&roster.label(player as usize)))?;
+                    }
+                    writeln!(output)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `{"rounds": [[[player, ...], ...], ...]}`, with players listed per table per round. With a
+/// roster the players are also listed as `"players": [{"name": ..., "metadata": {...}}, ...]`
+#[derive(Clone, Debug, Default)]
+pub struct JsonExporter {
+    pub pretty: bool,
+    pub roster: Option<Roster>,
+}
+
+#[derive(Serialize)]
+struct NamedSchedule<'a> {
+    rounds: &'a ScheduleGrid,
+    players: &'a [Player],
+}
+
+impl Exporter for JsonExporter {
+    fn export(&self, schedule: &Schedule, output: &mut dyn Write) -> Result<(), ExportError> {
+        match (self.roster.as_ref(), self.pretty) {
+            (Some(roster), pretty) => {
+                let named = NamedSchedule {
+                    rounds: schedule.rounds(),
+                    players: roster.players(),
+                };
+                if pretty {
+                    serde_json::to_writer_pretty(&mut *output, &named)?;
+                } else {
+                    serde_json::to_writer(&mut *output, &named)?;
+                }
+            }
+            (None, true) => serde_json::to_writer_pretty(&mut *output, schedule)?,
+            (None, false) => serde_json::to_writer(&mut *output, schedule)?,
+        }
+        writeln!(output)?;
+        Ok(())
+    }
+}
+
+/// A table with a row per round and a column per table
+#[derive(Clone, Debug, Default)]
+pub struct MarkdownExporter {
+    pub roster: Option<Roster>,
+}
+
+impl Exporter for MarkdownExporter {
+    fn export(&self, schedule: &Schedule, output: &mut dyn Write) -> Result<(), ExportError> {
+        let table_count = table_count(schedule.rounds());
+        write!(output, "| Round |")?;
+        for table in 0..table_count {
+            write!(output, " Table {} |", table + 1)?;
+        }
+        write!(output, "\n| ---: |")?;
+        for _ in 0..table_count {
+            write!(output, " --- |")?;
+        }
+        writeln!(output)?;
+        for (round, tables) in schedule.rounds().iter().enumerate() {
+            write!(output, "| {} |", round + 1)?;
+            for table in 0..table_count {
+                let players: Vec<String> = tables
+                    .get(table)
+                    .into_iter()
+                    .flatten()
+                    .map(|&player| label(self.roster.as_ref(), player as usize).replace('|', "\\|"))
+                    .collect();
+                write!(output, " {} |", players.join(", "))?;
+            }
+            writeln!(output)?;
+        }
+        Ok(())
+    }
+}
+
+/// The grid written by `State::format_schedule`, with columns widened to fit names
+#[derive(Clone, Debug, Default)]
+pub struct GridExporter {
+    pub roster: Option<Roster>,
+}
+
+impl Exporter for GridExporter {
+    fn export(&self, schedule: &Schedule, output: &mut dyn Write) -> Result<(), ExportError> {
+        let max_seats: Vec<usize> = schedule
+            .rounds()
+            .iter()
+            .map(|tables| tables.iter().map(Vec::len).max().unwrap_or(0))
+            .collect();
+        let mut grid = String::new();
+        write_grid(
+            &mut grid,
+            schedule.rounds(),
+            table_count(schedule.rounds()),
+            &max_seats,
+            |_, _, _, player| label(self.roster.as_ref(), player as usize),
+        )
+        .expect("writing to a String cannot fail");
+        output.write_all(grid.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Writes a row per seat and a column per table, with a line of players for each of the
+/// `max_seats` of a round and a blank line after them. `cell` gives the text for the player at
+/// a round, table and seat
+pub(crate) fn write_grid<W, F>(
+    output: &mut W,
+    rounds: &ScheduleGrid,
+    table_count: usize,
+    max_seats: &[usize],
+    cell: F,
+) -> core::fmt::Result
+where
+    W: core::fmt::Write + ?Sized,
+    F: Fn(usize, usize, usize, u8) -> String,
+{
+    let mut width = 3;
+    for (round, tables) in rounds.iter().enumerate() {
+        for (table, players) in tables.iter().enumerate() {
+            for (seat, &player) in players.iter().enumerate() {
+                width = width.max(cell(round, table, seat, player).chars().count());
+            }
+        }
+    }
+    let write_cell = |output: &mut W, text: &str, width: usize| {
+        for _ in text.chars().count()..width {
+            output.write_char(' ')?;
+        }
+        output.write_str(text)?;
+        output.write_str("  ")
+    };
+
+    output.write_str("     ")?;
+    for table in 0..table_count {
+        output.write_char('|')?;
+        write_cell(output, &(table + 1).to_string(), width)?;
+    }
+
+    for (round, &max_seats) in max_seats.iter().enumerate() {
+        output.write_str("\n-----")?;
+        for _ in 0..table_count {
+            output.write_char('+')?;
+            for _ in 0..width + 2 {
+                output.write_char('-')?;
+            }
+        }
+        for i in 0..max_seats + 1 {
+            if i == max_seats.div_ceil(2) {
+                output.write_char('\n')?;
+                write_cell(output, &(round + 1).to_string(), 3)?;
+            } else {
+                output.write_str("\n     ")?;
+            }
+            for table in 0..table_count {
+                output.write_char('|')?;
+                let player = rounds
+                    .get(round)
+                    .and_then(|tables| tables.get(table))
+                    .and_then(|players| players.get(i));
+                match player {
+                    Some(&player) => write_cell(output, &cell(round, table, i, player), width)?,
+                    None => write_cell(output, "", width)?,
+                }
+            }
+        }
+    }
+    Ok(())
+}
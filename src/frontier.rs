@@ -0,0 +1,308 @@
+//! Searching with `State::bstep` while keeping unexplored states on disk once they outgrow
+//! memory
+//!
+//! States are bucketed by `State::get_available_count`, which never grows from a state to the
+//! states `bstep` makes from it. Buckets are explored from the most available players down, so
+//! nothing is added to a bucket once it is being explored and its file can be removed when it is
+//! finished.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use thiserror::Error;
+
+use crate::limits::{self, Stepwise};
+use crate::{
+    Config, NoObserver, PlayerSet, Progress, Schedule, SearchLimits, SearchObserver,
+    SearchOutcome, SearchStats, SearchStep, State,
+};
+
+/// States kept in memory by `FrontierSearch` unless told otherwise
+pub const DEFAULT_MEMORY_BUDGET: usize = 100_000;
+
+/// Gives each search in the process its own directory
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Error)]
+pub enum FrontierError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Encoding(#[from] bincode::Error),
+}
+
+/// Unexplored states which all have the same available count
+#[derive(Debug)]
+struct Bucket<S> {
+    states: Vec<State<S>>,
+    writer: Option<BufWriter<File>>,
+    reader: Option<BufReader<File>>,
+    /// States written to disk and not read back yet
+    spilled: u64,
+}
+
+impl<S> Default for Bucket<S> {
+    fn default() -> Self {
+        Self {
+            states: Vec::new(),
+            writer: None,
+            reader: None,
+            spilled: 0,
+        }
+    }
+}
+
+/// Finds every schedule reachable from a state, like `StateSolutions`, but writes states to disk
+/// rather than holding more than the memory budget. Its files are removed as it goes and the
+/// rest when it is dropped
+#[derive(Debug)]
+pub struct FrontierSearch<S = u32> {
+    /// Created for this search alone
+    directory: PathBuf,
+    /// Shared by every state, so states are written to disk without it
+    config: Arc<Config>,
+    /// Indexed by available count, with the last being explored
+    buckets: Vec<Bucket<S>>,
+    memory_budget: usize,
+    /// States in memory in every bucket but the one being explored
+    in_memory: usize,
+    stats: SearchStats,
+    started: Instant,
+}
+
+impl<S: PlayerSet> FrontierSearch<S> {
+    /// Spills states into a new directory inside `directory`, which is created if needed
+    pub fn new(state: State<S>, directory: &Path) -> Result<Self, FrontierError> {
+        let directory = directory.join(format!(
+            "frontier-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&directory)?;
+        let available_count = state.get_available_count() as usize;
+        let mut buckets: Vec<Bucket<S>> =
+            (0..=available_count).map(|_| Bucket::default()).collect();
+        let config = Arc::clone(&state.config);
+        buckets[available_count].states.push(state);
+        Ok(Self {
+            directory,
+            config,
+            buckets,
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            in_memory: 0,
+            stats: SearchStats::default(),
+            started: Instant::now(),
+        })
+    }
+
+    /// States to keep in memory before writing new ones to disk. States in the bucket being
+    /// explored are always kept in memory, but are only as many as the search is deep
+    pub fn with_memory_budget(mut self, memory_budget: usize) -> Self {
+        self.memory_budget = memory_budget;
+        self
+    }
+
+    /// Where states are written, which is removed along with the search
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// States waiting on disk
+    pub fn spilled(&self) -> u64 {
+        self.buckets.iter().map(|bucket| bucket.spilled).sum()
+    }
+
+    /// Progress since the search was made, where a backtrack is a state with nothing below it
+    pub fn stats(&self) -> SearchStats {
+        SearchStats {
+            elapsed: self.started.elapsed(),
+            ..self.stats.clone()
+        }
+    }
+
+    /// Explores a single state, so callers can stop between steps
+    pub fn step(&mut self) -> Result<SearchStep, FrontierError> {
+        self.step_observed(&mut NoObserver)
+    }
+
+    /// Like `step`, telling `observer` what the search does. The players each new state adds to
+    /// the one explored are reported as placed, and states are not returned to, so nothing is
+    /// removed or backtracked
+    pub fn step_observed<O: SearchObserver>(
+        &mut self,
+        observer: &mut O,
+    ) -> Result<SearchStep, FrontierError> {
+        let mut state = match self.next_state()? {
+            Some(state) => state,
+            None => return Ok(SearchStep::Finished),
+        };
+        self.stats
+            .record(state.get_players_played_count() as usize, || {
+                state.schedule()
+            });
+        if state.is_complete() {
+            self.stats.solutions += 1;
+            let schedule = state.schedule();
+            observer.solution_found(&schedule);
+            return Ok(SearchStep::Found(schedule));
+        }
+        let before = if observer.watches_players() {
+            Some(state.clone())
+        } else {
+            None
+        };
+        let mut result = Ok(());
+        let mut children = 0;
+        state.bstep(&mut |next: &State<S>| {
+            children += 1;
+            if let Some(before) = &before {
+                next.for_each_player_not_in(before, |round, table, player| {
+                    observer.placed(round, table, player)
+                });
+            }
+            if result.is_ok() {
+                result = self.push(next);
+            }
+        });
+        result?;
+        if children == 0 {
+            self.stats.backtracks += 1;
+            observer.dead_end(state.get_players_played_count() as usize);
+        }
+        Ok(SearchStep::Searching)
+    }
+
+    /// Explores until a schedule is found, every state has been explored or a limit is reached
+    pub fn find(&mut self, limits: &SearchLimits) -> Result<SearchOutcome, FrontierError> {
+        limits::find(self, limits)
+    }
+
+    fn bucket_path(&self, available_count: usize) -> PathBuf {
+        self.directory
+            .join(format!("bucket{}.bin", available_count))
+    }
+
+    fn push(&mut self, state: &State<S>) -> Result<(), FrontierError> {
+        let available_count = state.get_available_count() as usize;
+        let current = self.buckets.len() - 1;
+        assert!(
+            available_count <= current,
+            "bstep made more players available"
+        );
+        if available_count == current || self.in_memory < self.memory_budget {
+            if available_count != current {
+                self.in_memory += 1;
+            }
+            self.buckets[available_count].states.push(state.clone());
+            return Ok(());
+        }
+        if self.buckets[available_count].writer.is_none() {
+            let file = File::create(self.bucket_path(available_count))?;
+            self.buckets[available_count].writer = Some(BufWriter::new(file));
+        }
+        let bucket = &mut self.buckets[available_count];
+        bincode::serialize_into(bucket.writer.as_mut().unwrap(), &state.progress)?;
+        bucket.spilled += 1;
+        Ok(())
+    }
+
+    /// Takes states from memory before disk, moving on to the next bucket when one runs out
+    fn next_state(&mut self) -> Result<Option<State<S>>, FrontierError> {
+        while let Some(bucket) = self.buckets.last_mut() {
+            if let Some(state) = bucket.states.pop() {
+                return Ok(Some(state));
+            }
+            let current = self.buckets.len() - 1;
+            let path = self.bucket_path(current);
+            let bucket = &mut self.buckets[current];
+            if bucket.spilled > 0 {
+                if let Some(mut writer) = bucket.writer.take() {
+                    writer.flush()?;
+                    bucket.reader = Some(BufReader::new(File::open(&path)?));
+                }
+                bucket.spilled -= 1;
+                let progress: Progress<S> =
+                    bincode::deserialize_from(bucket.reader.as_mut().unwrap())?;
+                return Ok(Some(State {
+                    config: Arc::clone(&self.config),
+                    progress,
+                }));
+            }
+            if bucket.reader.take().is_some() {
+                std::fs::remove_file(&path)?;
+            }
+            self.buckets.pop();
+            if let Some(bucket) = self.buckets.last() {
+                self.in_memory -= bucket.states.len();
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<S: PlayerSet> Stepwise for FrontierSearch<S> {
+    type Error = FrontierError;
+
+    fn step(&mut self) -> Result<SearchStep, Self::Error> {
+        FrontierSearch::step(self)
+    }
+
+    fn counts(&self) -> &SearchStats {
+        &self.stats
+    }
+
+    fn started(&self) -> Instant {
+        self.started
+    }
+}
+
+impl<S: PlayerSet> Iterator for FrontierSearch<S> {
+    type Item = Result<Schedule, FrontierError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.step() {
+                Ok(SearchStep::Searching) => {}
+                Ok(SearchStep::Found(schedule)) => return Some(Ok(schedule)),
+                Ok(SearchStep::Finished) => return None,
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+impl<S> Drop for FrontierSearch<S> {
+    fn drop(&mut self) {
+        // Files must be closed before they can be removed on some platforms
+        self.buckets.clear();
+        if let Err(error) = std::fs::remove_dir_all(&self.directory) {
+            log::warn!("Could not remove {}: {}", self.directory.display(), error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_schedule_with_met_players() {
+        // Not a state bstep makes, so a table can have players in its potential who cannot join
+        let config = Config::new(3, 4, 3).unwrap();
+        let schedule = Schedule::new(vec![
+            vec![vec![0], vec![], vec![], vec![]],
+            vec![vec![], vec![0, 4], vec![], vec![]],
+        ]);
+        let state: State = State::from_schedule(config.clone(), &schedule).unwrap();
+        let mut search = FrontierSearch::new(state, &std::env::temp_dir()).unwrap();
+        for _ in 0..1000 {
+            if let SearchStep::Found(schedule) = search.step().unwrap() {
+                assert!(schedule.validate(&config).is_empty());
+            }
+        }
+    }
+}
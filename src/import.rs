@@ -0,0 +1,280 @@
+//! Reading schedules back from the formats they are written in
+//!
+//! Lines and cells in errors are numbered from 1, as they would be in an editor or spreadsheet.
+
+use thiserror::Error;
+
+use crate::config::MAX_COUNT;
+use crate::{Schedule, ScheduleGrid};
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("Line {line}: expected the header {expected:?}")]
+    BadHeader { line: usize, expected: &'static str },
+    #[error("Line {line} has {found} cells but {expected} were expected")]
+    WrongCellCount {
+        line: usize,
+        found: usize,
+        expected: usize,
+    },
+    #[error("Line {line}, cell {cell}: {text:?} is not a valid number")]
+    BadNumber {
+        line: usize,
+        cell: usize,
+        text: String,
+    },
+    #[error("Line {line}, cell {cell}: {index} is more than the largest index allowed, {max}")]
+    IndexTooLarge {
+        line: usize,
+        cell: usize,
+        index: usize,
+        max: usize,
+    },
+    #[error("Line {line}: a quoted cell is never closed")]
+    UnclosedQuote { line: usize },
+    #[error("Line {line}: players are listed before the first round separator")]
+    OutsideRound { line: usize },
+    #[error("Line {line}: seat {seat} at table {table} in round {round} is already filled")]
+    DuplicateSeat {
+        line: usize,
+        round: usize,
+        table: usize,
+        seat: usize,
+    },
+    #[error(
+        "Line {line}: seat {seat} at table {table} in round {round} is filled but seat \
+         {missing} is not"
+    )]
+    MissingSeat {
+        line: usize,
+        round: usize,
+        table: usize,
+        seat: usize,
+        missing: usize,
+    },
+}
+
+/// Reads a schedule in some format
+pub trait Importer {
+    fn import(&self, input: &str) -> Result<Schedule, ImportError>;
+}
+
+/// The grid written by `State::format_schedule`, also used for `State`'s `Display`. The cursor
+/// brackets and `.` empty seats in `DF2`'s grid are read past, leaving the players placed
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GridImporter;
+
+impl Importer for GridImporter {
+    fn import(&self, input: &str) -> Result<Schedule, ImportError> {
+        let mut lines = input.lines().enumerate().map(|(i, line)| (i + 1, line));
+        let table_count = match lines.next() {
+            Some((_, header)) if header.contains('|') => header.split('|').count() - 1,
+            _ => {
+                return Err(ImportError::BadHeader {
+                    line: 1,
+                    expected: "|  1  |  2  ...",
+                })
+            }
+        };
+        let mut rounds: ScheduleGrid = Vec::new();
+        for (line, text) in lines {
+            if text.trim().is_empty() {
+                continue;
+            }
+            if text.starts_with('-') {
+                rounds.push(vec![Vec::new(); table_count]);
+                continue;
+            }
+            let tables = rounds
+                .last_mut()
+                .ok_or(ImportError::OutsideRound { line })?;
+            // The first cell holds the round number on one line of each round
+            let cells: Vec<&str> = text.split('|').skip(1).collect();
+            if cells.len() != table_count {
+                return Err(ImportError::WrongCellCount {
+                    line,
+                    found: cells.len() + 1,
+                    expected: table_count + 1,
+                });
+            }
+            for (table, cell) in cells.iter().enumerate() {
+                let cell = cell.trim();
+                let cell = cell
+                    .strip_prefix('[')
+                    .and_then(|cell| cell.strip_suffix(']'))
+                    .unwrap_or(cell);
+                if !cell.is_empty() && cell != "." {
+                    tables[table].push(parse(line, table + 2, cell)?);
+                }
+            }
+        }
+        Ok(Schedule::new(rounds))
+    }
+}
+
+/// The `round,table,seat,player` lines written by `CsvExporter`, in any order
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CsvImporter;
+
+impl CsvImporter {
+    const HEADER: &'static str = "round,table,seat,player";
+}
+
+impl Importer for CsvImporter {
+    fn import(&self, input: &str) -> Result<Schedule, ImportError> {
+        let mut lines = input.lines().enumerate().map(|(i, line)| (i + 1, line));
+        // Later columns, such as player names, are ignored
+        let column_count = match lines.next().and_then(|(_, header)| split_csv_line(header)) {
+            Some(header) if header.len() >= 4 && header[..4].join(",") == Self::HEADER => {
+                header.len()
+            }
+            _ => {
+                return Err(ImportError::BadHeader {
+                    line: 1,
+                    expected: Self::HEADER,
+                })
+            }
+        };
+        // Line each seat was read from, indexed by round, table then seat
+        let mut seats = Vec::new();
+        for (line, text) in lines {
+            if text.trim().is_empty() {
+                continue;
+            }
+            let cells = split_csv_line(text).ok_or(ImportError::UnclosedQuote { line })?;
+            if cells.len() != column_count {
+                return Err(ImportError::WrongCellCount {
+                    line,
+                    found: cells.len(),
+                    expected: column_count,
+                });
+            }
+            let round = parse_index(line, 1, &cells[0])?;
+            let table = parse_index(line, 2, &cells[1])?;
+            let seat = parse_index(line, 3, &cells[2])?;
+            let player: u8 = parse(line, 4, &cells[3])?;
+            if seats.len() <= round {
+                seats.resize(round + 1, Vec::new());
+            }
+            let tables = &mut seats[round];
+            if tables.len() <= table {
+                tables.resize(table + 1, Vec::new());
+            }
+            let players = &mut tables[table];
+            if players.len() <= seat {
+                players.resize(seat + 1, None);
+            }
+            if players[seat].is_some() {
+                return Err(ImportError::DuplicateSeat {
+                    line,
+                    round,
+                    table,
+                    seat,
+                });
+            }
+            players[seat] = Some((line, player));
+        }
+
+        let table_count = seats.iter().map(Vec::len).max().unwrap_or(0);
+        let mut rounds: ScheduleGrid = Vec::new();
+        for (round, tables) in seats.into_iter().enumerate() {
+            let mut players_at = vec![Vec::new(); table_count];
+            for (table, players) in tables.into_iter().enumerate() {
+                let missing = players.iter().position(Option::is_none);
+                if let Some(missing) = missing {
+                    // The last seat is always filled, so report the line that skipped a seat
+                    let (seat, &(line, _)) = players
+                        .iter()
+                        .enumerate()
+                        .skip(missing)
+                        .find_map(|(seat, entry)| entry.as_ref().map(|entry| (seat, entry)))
+                        .unwrap();
+                    return Err(ImportError::MissingSeat {
+                        line,
+                        round,
+                        table,
+                        seat,
+                        missing,
+                    });
+                }
+                players_at[table] = players.into_iter().flatten().map(|(_, p)| p).collect();
+            }
+            rounds.push(players_at);
+        }
+        Ok(Schedule::new(rounds))
+    }
+}
+
+/// The JSON written by `JsonExporter`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonImporter;
+
+impl Importer for JsonImporter {
+    fn import(&self, input: &str) -> Result<Schedule, ImportError> {
+        Ok(serde_json::from_str(input)?)
+    }
+}
+
+fn parse<T: std::str::FromStr>(line: usize, cell: usize, text: &str) -> Result<T, ImportError> {
+    text.parse().map_err(|_| ImportError::BadNumber {
+        line,
+        cell,
+        text: text.to_string(),
+    })
+}
+
+/// A round, table or seat number, bounded as in `Config` so that it is safe to size arrays by
+fn parse_index(line: usize, cell: usize, text: &str) -> Result<usize, ImportError> {
+    let index = parse(line, cell, text)?;
+    if index >= MAX_COUNT {
+        return Err(ImportError::IndexTooLarge {
+            line,
+            cell,
+            index,
+            max: MAX_COUNT - 1,
+        });
+    }
+    Ok(index)
+}
+
+/// Splits a CSV line into trimmed cells, where quoted cells may hold commas and `""` stands for
+/// a quote. Returns `None` if a quoted cell is never closed
+pub(crate) fn split_csv_line(line: &str) -> Option<Vec<String>> {
+    let mut cells = Vec::new();
+    let mut chars = line.chars().peekable();
+    loop {
+        let mut cell = String::new();
+        while chars.peek() == Some(&' ') {
+            chars.next();
+        }
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next()? {
+                    '"' if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        cell.push('"');
+                    }
+                    '"' => break,
+                    c => cell.push(c),
+                }
+            }
+        }
+        let mut rest = String::new();
+        let mut last = true;
+        for c in chars.by_ref() {
+            if c == ',' {
+                last = false;
+                break;
+            }
+            rest.push(c);
+        }
+        cell.push_str(rest.trim());
+        cells.push(cell);
+        if last {
+            return Some(cells);
+        }
+    }
+}
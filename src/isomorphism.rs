@@ -0,0 +1,445 @@
+//! Rejecting partial schedules that only differ by relabelling players, reordering rounds or
+//! reordering tables
+//!
+//! Rounds, tables and players are split into classes by what can be told about them without
+//! their labels, such as how many players sit at a table and who they meet, so that only rounds
+//! and tables which cannot be told apart are tried in different orders. Orders which give the
+//! same form show a symmetry of the schedule, and orders that the symmetries already account
+//! for are skipped.
+
+use std::collections::HashSet;
+
+use crate::{Config, ConfigError, PlayerSet, State, WidePlayerSet};
+
+/// Partial schedule with players relabelled and rounds and tables reordered to give the smallest
+/// form, so isomorphic schedules have equal forms
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct CanonicalForm(Vec<Vec<u8>>);
+
+pub struct IsomorphismFilter {
+    player_count: usize,
+    /// Classes rounds start in, by the table sizes in them, or a class each if rounds cannot be
+    /// reordered
+    round_classes: Vec<usize>,
+    /// Classes tables start in, by their sizes in each round
+    table_classes: Vec<usize>,
+    seen: HashSet<CanonicalForm>,
+}
+
+impl IsomorphismFilter {
+    pub fn new(config: &Config) -> Result<Self, ConfigError> {
+        if !config.players_interchangeable() {
+            return Err(ConfigError::PlayersNotInterchangeable);
+        }
+        // Rounds and tables can only be swapped with others of the same sizes
+        let round_classes = if config.rounds_interchangeable() {
+            rank(
+                config
+                    .rounds()
+                    .map(|round| {
+                        config
+                            .tables()
+                            .map(|table| config.seats(round, table))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect(),
+            )
+        } else {
+            (0..config.round_count()).collect()
+        };
+        let table_classes = rank(
+            config
+                .tables()
+                .map(|table| {
+                    config
+                        .rounds()
+                        .map(|round| config.seats(round, table))
+                        .collect::<Vec<_>>()
+                })
+                .collect(),
+        );
+        Ok(Self {
+            player_count: config.player_count(),
+            round_classes,
+            table_classes,
+            seen: HashSet::new(),
+        })
+    }
+
+    /// Canonical form of the players placed and the players still able to play at each table
+    pub fn canonical_form<S: PlayerSet>(&self, state: &State<S>) -> CanonicalForm {
+        self.canonical_cells(
+            &state.progress.played_on_table,
+            &state.progress.potential_on_table,
+        )
+    }
+
+    /// Canonical form of a schedule indexed by round then table, where each table lists its
+    /// players
+    pub fn canonical_schedule(&self, schedule: &[Vec<Vec<u8>>]) -> CanonicalForm {
+        let played: Vec<Vec<WidePlayerSet<4>>> = schedule
+            .iter()
+            .map(|tables| {
+                tables
+                    .iter()
+                    .map(|players| {
+                        let mut set = WidePlayerSet::empty();
+                        for &player in players.iter() {
+                            set.insert(player as usize);
+                        }
+                        set
+                    })
+                    .collect()
+            })
+            .collect();
+        self.canonical_cells(&played, &played)
+    }
+
+    fn canonical_cells<S: PlayerSet>(
+        &self,
+        played: &[Vec<S>],
+        potential: &[Vec<S>],
+    ) -> CanonicalForm {
+        let search = self.label_search(played, potential);
+        CanonicalForm(search.best.unwrap().form)
+    }
+
+    /// Number of orders of rounds and tables tried to put the state in canonical form
+    #[cfg(test)]
+    pub(crate) fn orders_tried<S: PlayerSet>(&self, state: &State<S>) -> usize {
+        self.label_search(
+            &state.progress.played_on_table,
+            &state.progress.potential_on_table,
+        )
+        .leaves
+    }
+
+    /// Each player is described by whether they played, or could still play, at each table in
+    /// each round. Sorting these descriptions removes the player labels
+    fn label_search<S: PlayerSet>(&self, played: &[Vec<S>], potential: &[Vec<S>]) -> LabelSearch {
+        let table_count = self.table_classes.len();
+        let mut cells = Vec::with_capacity(played.len() * table_count * self.player_count);
+        for (played, potential) in played.iter().zip(potential) {
+            for (played, potential) in played.iter().zip(potential) {
+                cells.extend((0..self.player_count).map(|player| {
+                    if played.contains(player) {
+                        2
+                    } else if potential.contains(player) {
+                        1
+                    } else {
+                        0
+                    }
+                }));
+            }
+        }
+        let mut search = LabelSearch {
+            cells,
+            round_count: self.round_classes.len(),
+            table_count,
+            player_count: self.player_count,
+            first: None,
+            best: None,
+            symmetries: Vec::new(),
+            leaves: 0,
+        };
+        let classes = Classes {
+            rounds: self.round_classes.clone(),
+            tables: self.table_classes.clone(),
+        };
+        search.search(classes, &mut Vec::new());
+        search
+    }
+
+    /// Records the state, returning whether no isomorphic state had been seen before
+    pub fn insert<S: PlayerSet>(&mut self, state: &State<S>) -> bool {
+        let form = self.canonical_form(state);
+        self.seen.insert(form)
+    }
+
+    /// Records the schedule, returning whether no isomorphic schedule had been seen before
+    pub fn insert_schedule(&mut self, schedule: &[Vec<Vec<u8>>]) -> bool {
+        let form = self.canonical_schedule(schedule);
+        self.seen.insert(form)
+    }
+
+    pub fn seen_count(&self) -> usize {
+        self.seen.len()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Axis {
+    Round,
+    Table,
+}
+
+/// Class of each round and table, numbered in the order the classes are put in the form. Rounds
+/// or tables in the same class have not been told apart yet
+#[derive(Clone, Debug)]
+struct Classes {
+    rounds: Vec<usize>,
+    tables: Vec<usize>,
+}
+
+impl Classes {
+    fn axis(&self, axis: Axis) -> &Vec<usize> {
+        match axis {
+            Axis::Round => &self.rounds,
+            Axis::Table => &self.tables,
+        }
+    }
+
+    fn axis_mut(&mut self, axis: Axis) -> &mut Vec<usize> {
+        match axis {
+            Axis::Round => &mut self.rounds,
+            Axis::Table => &mut self.tables,
+        }
+    }
+
+    /// Rounds then tables listed by class, which is their order once every class has one member
+    fn order(&self) -> (Vec<usize>, Vec<usize>) {
+        let by_class = |classes: &[usize]| {
+            let mut items: Vec<usize> = (0..classes.len()).collect();
+            items.sort_by_key(|&item| classes[item]);
+            items
+        };
+        (by_class(&self.rounds), by_class(&self.tables))
+    }
+}
+
+/// A form found by putting rounds and tables in an order
+struct Leaf {
+    form: Vec<Vec<u8>>,
+    rounds: Vec<usize>,
+    tables: Vec<usize>,
+}
+
+/// A reordering of rounds and tables which, with some relabelling of players, leaves the
+/// schedule as it was
+struct Symmetry {
+    rounds: Vec<usize>,
+    tables: Vec<usize>,
+}
+
+impl Symmetry {
+    fn axis(&self, axis: Axis) -> &[usize] {
+        match axis {
+            Axis::Round => &self.rounds,
+            Axis::Table => &self.tables,
+        }
+    }
+}
+
+/// The class of a round, table or player with the cells it has, each given by the classes of the
+/// other two and the cell
+type SignatureKey = (usize, Vec<(usize, usize, u8)>);
+
+/// Search for the smallest form over the orders that refining classes leaves open
+struct LabelSearch {
+    /// 2 if the player played, 1 if they could still play and 0 otherwise, indexed by round,
+    /// table then player
+    cells: Vec<u8>,
+    round_count: usize,
+    table_count: usize,
+    player_count: usize,
+    first: Option<Leaf>,
+    best: Option<Leaf>,
+    symmetries: Vec<Symmetry>,
+    /// Orders tried so far
+    leaves: usize,
+}
+
+impl LabelSearch {
+    fn cell(&self, round: usize, table: usize, player: usize) -> u8 {
+        self.cells[(round * self.table_count + table) * self.player_count + player]
+    }
+
+    /// Splits classes by who plays where, in terms of the classes of the other rounds, tables
+    /// and players, until no more can be split
+    fn refine(&self, classes: &mut Classes) {
+        let mut players = vec![0; self.player_count];
+        let mut counts = (0, 0, 0);
+        loop {
+            let mut player_keys: Vec<SignatureKey> =
+                players.iter().map(|&class| (class, Vec::new())).collect();
+            let mut round_keys: Vec<SignatureKey> = classes
+                .rounds
+                .iter()
+                .map(|&class| (class, Vec::new()))
+                .collect();
+            let mut table_keys: Vec<SignatureKey> = classes
+                .tables
+                .iter()
+                .map(|&class| (class, Vec::new()))
+                .collect();
+            for (round, round_key) in round_keys.iter_mut().enumerate() {
+                let round_class = round_key.0;
+                for (table, table_key) in table_keys.iter_mut().enumerate() {
+                    let table_class = table_key.0;
+                    for (player, &player_class) in players.iter().enumerate() {
+                        let cell = self.cell(round, table, player);
+                        if cell != 0 {
+                            player_keys[player].1.push((round_class, table_class, cell));
+                            round_key.1.push((table_class, player_class, cell));
+                            table_key.1.push((round_class, player_class, cell));
+                        }
+                    }
+                }
+            }
+            for (_, key) in player_keys
+                .iter_mut()
+                .chain(round_keys.iter_mut())
+                .chain(table_keys.iter_mut())
+            {
+                key.sort_unstable();
+            }
+            players = rank(player_keys);
+            classes.rounds = rank(round_keys);
+            classes.tables = rank(table_keys);
+            let new_counts = (
+                class_count(&players),
+                class_count(&classes.rounds),
+                class_count(&classes.tables),
+            );
+            if new_counts == counts {
+                return;
+            }
+            counts = new_counts;
+        }
+    }
+
+    /// Tries each way of putting a round or table first in its class, as far as no symmetry
+    /// found so far makes two ways equivalent, keeping the smallest form
+    fn search(&mut self, mut classes: Classes, path: &mut Vec<(Axis, usize)>) {
+        self.refine(&mut classes);
+        let target = [Axis::Round, Axis::Table].iter().find_map(|&axis| {
+            let items = classes.axis(axis);
+            (0..items.len())
+                .filter(|&item| items.iter().filter(|&&class| class == items[item]).count() > 1)
+                .min_by_key(|&item| items[item])
+                .map(|item| (axis, items[item]))
+        });
+        let (axis, class) = match target {
+            Some(target) => target,
+            None => return self.leaf(&classes),
+        };
+        let members: Vec<usize> = (0..classes.axis(axis).len())
+            .filter(|&item| classes.axis(axis)[item] == class)
+            .collect();
+        let mut tried: Vec<usize> = Vec::new();
+        for &member in members.iter() {
+            if tried
+                .iter()
+                .any(|&other| self.equivalent(path, axis, other, member))
+            {
+                continue;
+            }
+            let mut individualised = classes.clone();
+            let items = individualised.axis_mut(axis);
+            *items = rank(
+                items
+                    .iter()
+                    .enumerate()
+                    .map(|(item, &class)| (class, item != member))
+                    .collect(),
+            );
+            path.push((axis, member));
+            self.search(individualised, path);
+            path.pop();
+            tried.push(member);
+        }
+    }
+
+    /// Whether a symmetry found so far which keeps every round and table on `path` in place maps
+    /// one item onto the other, perhaps through others, so they lead to the same forms
+    fn equivalent(&self, path: &[(Axis, usize)], axis: Axis, first: usize, second: usize) -> bool {
+        let length = match axis {
+            Axis::Round => self.round_count,
+            Axis::Table => self.table_count,
+        };
+        let mut orbit: Vec<usize> = (0..length).collect();
+        fn root(orbit: &mut [usize], mut item: usize) -> usize {
+            while orbit[item] != item {
+                orbit[item] = orbit[orbit[item]];
+                item = orbit[item];
+            }
+            item
+        }
+        for symmetry in self.symmetries.iter() {
+            if path
+                .iter()
+                .any(|&(axis, item)| symmetry.axis(axis)[item] != item)
+            {
+                continue;
+            }
+            for (item, &image) in symmetry.axis(axis).iter().enumerate() {
+                let (item, image) = (root(&mut orbit, item), root(&mut orbit, image));
+                orbit[item] = image;
+            }
+        }
+        root(&mut orbit, first) == root(&mut orbit, second)
+    }
+
+    fn leaf(&mut self, classes: &Classes) {
+        self.leaves += 1;
+        let (rounds, tables) = classes.order();
+        let mut form: Vec<Vec<u8>> = (0..self.player_count)
+            .map(|player| {
+                let mut row = Vec::with_capacity(rounds.len() * tables.len());
+                for &round in rounds.iter() {
+                    row.extend(tables.iter().map(|&table| self.cell(round, table, player)));
+                }
+                row
+            })
+            .collect();
+        form.sort_unstable();
+        let leaf = Leaf {
+            form,
+            rounds,
+            tables,
+        };
+        for known in self.first.iter().chain(self.best.iter()) {
+            if known.form == leaf.form {
+                let symmetry = Symmetry {
+                    rounds: mapping(&known.rounds, &leaf.rounds),
+                    tables: mapping(&known.tables, &leaf.tables),
+                };
+                self.symmetries.push(symmetry);
+                return;
+            }
+        }
+        if self.first.is_none() {
+            self.first = Some(Leaf {
+                form: leaf.form.clone(),
+                rounds: leaf.rounds.clone(),
+                tables: leaf.tables.clone(),
+            });
+        }
+        if self.best.as_ref().is_none_or(|best| leaf.form < best.form) {
+            self.best = Some(leaf);
+        }
+    }
+}
+
+/// Numbers the distinct keys in order, so that items with equal keys share a number
+fn rank<K: Ord>(keys: Vec<K>) -> Vec<usize> {
+    let mut sorted: Vec<&K> = keys.iter().collect();
+    sorted.sort_unstable();
+    sorted.dedup();
+    keys.iter()
+        .map(|key| sorted.binary_search(&key).unwrap())
+        .collect()
+}
+
+fn class_count(classes: &[usize]) -> usize {
+    classes.iter().max().map_or(0, |&max| max + 1)
+}
+
+/// The permutation taking the items of one order to those in the same positions in another
+fn mapping(from: &[usize], to: &[usize]) -> Vec<usize> {
+    let mut mapping = vec![0; from.len()];
+    for (&from, &to) in from.iter().zip(to) {
+        mapping[from] = to;
+    }
+    mapping
+}
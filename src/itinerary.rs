@@ -0,0 +1,176 @@
+//! Where each player goes in each round and who they play with, for handing out to players
+
+use std::io::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::export::{csv_cell, label, ExportError, Exporter};
+use crate::{Roster, Schedule};
+
+/// A player's place in one round
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Stop {
+    pub round: usize,
+    /// `None` when sitting out
+    pub table: Option<usize>,
+    pub tablemates: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Itinerary {
+    pub player: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub stops: Vec<Stop>,
+}
+
+impl Schedule {
+    /// Players seated anywhere in the schedule are numbered below this
+    pub fn player_count(&self) -> usize {
+        self.rounds()
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|&player| player as usize + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn itinerary(&self, player: usize) -> Itinerary {
+        let stops = self
+            .rounds()
+            .iter()
+            .enumerate()
+            .map(|(round, tables)| {
+                let table = tables
+                    .iter()
+                    .position(|players| players.iter().any(|&other| other as usize == player));
+                let tablemates = table
+                    .map(|table| {
+                        tables[table]
+                            .iter()
+                            .copied()
+                            .filter(|&other| other as usize != player)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Stop {
+                    round,
+                    table,
+                    tablemates,
+                }
+            })
+            .collect();
+        Itinerary {
+            player,
+            name: None,
+            stops,
+        }
+    }
+
+    /// Itineraries of every player, named from the roster if there is one
+    pub fn itineraries(&self, roster: Option<&Roster>) -> Vec<Itinerary> {
+        let player_count = roster.map_or(0, Roster::len).max(self.player_count());
+        (0..player_count)
+            .map(|player| Itinerary {
+                name: roster.map(|roster| roster.label(player)),
+                ..self.itinerary(player)
+            })
+            .collect()
+    }
+}
+
+/// A paragraph per player, numbering rounds and tables from 1
+#[derive(Clone, Debug, Default)]
+pub struct TextItineraryExporter {
+    pub roster: Option<Roster>,
+}
+
+impl Exporter for TextItineraryExporter {
+    fn export(&self, schedule: &Schedule, output: &mut dyn Write) -> Result<(), ExportError> {
+        let roster = self.roster.as_ref();
+        for (i, itinerary) in schedule.itineraries(roster).iter().enumerate() {
+            if i != 0 {
+                writeln!(output)?;
+            }
+            writeln!(output, "{}", label(roster, itinerary.player))?;
+            for stop in itinerary.stops.iter() {
+                write!(output, "  Round {}: ", stop.round + 1)?;
+                match stop.table {
+                    Some(table) => {
+                        let tablemates: Vec<String> = stop
+                            .tablemates
+                            .iter()
+                            .map(|&other| label(roster, other as usize))
+                            .collect();
+                        writeln!(output, "table {} with {}", table + 1, tablemates.join(", "))?;
+                    }
+                    None => writeln!(output, "sitting out")?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One `player,round,table,tablemates` line per player per round, with tablemates separated by
+/// `;` and the table left empty when sitting out. Names are added as a `name` column, and used
+/// for tablemates, when there is a roster
+#[derive(Clone, Debug, Default)]
+pub struct CsvItineraryExporter {
+    pub roster: Option<Roster>,
+}
+
+impl Exporter for CsvItineraryExporter {
+    fn export(&self, schedule: &Schedule, output: &mut dyn Write) -> Result<(), ExportError> {
+        let roster = self.roster.as_ref();
+        write!(output, "player,")?;
+        if roster.is_some() {
+            write!(output, "name,")?;
+        }
+        writeln!(output, "round,table,tablemates")?;
+        for itinerary in schedule.itineraries(roster).iter() {
+            for stop in itinerary.stops.iter() {
+                write!(output, "{},", itinerary.player)?;
+                if let Some(name) = itinerary.name.as_ref() {
+                    write!(output, "{},", csv_cell(name))?;
+                }
+                let table = stop.table.map(|table| table.to_string());
+                let tablemates: Vec<String> = stop
+                    .tablemates
+                    .iter()
+                    .map(|&other| label(roster, other as usize))
+                    .collect();
+                writeln!(
+                    output,
+                    "{},{},{}",
+                    stop.round,
+                    table.unwrap_or_default(),
+                    csv_cell(&tablemates.join(";"))
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A list of `{"player": ..., "name": ..., "stops": [{"round": ..., "table": ...,
+/// "tablemates": [...]}, ...]}`, with `name` only present when there is a roster
+#[derive(Clone, Debug, Default)]
+pub struct JsonItineraryExporter {
+    pub pretty: bool,
+    pub roster: Option<Roster>,
+}
+
+impl Exporter for JsonItineraryExporter {
+    fn export(&self, schedule: &Schedule, output: &mut dyn Write) -> Result<(), ExportError> {
+        let itineraries = schedule.itineraries(self.roster.as_ref());
+        if self.pretty {
+            serde_json::to_writer_pretty(&mut *output, &itineraries)?;
+        } else {
+            serde_json::to_writer(&mut *output, &itineraries)?;
+        }
+        writeln!(output)?;
+        Ok(())
+    }
+}
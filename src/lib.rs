@@ -1,83 +1,114 @@
+mod checkpoint;
+mod config;
+mod export;
+mod frontier;
+mod import;
+mod isomorphism;
+mod itinerary;
+mod limits;
+mod meetings;
+mod observer;
+mod parallel;
+mod player_set;
+mod roster;
+mod schedule;
+mod sit_out;
+mod solutions;
+mod stats;
 mod to_explore;
 
+pub use checkpoint::{Checkpoint, CheckpointError, CHECKPOINT_VERSION};
+pub use config::{Config, Round, Table};
+pub use export::{
+    CsvExporter, ExportError, Exporter, GridExporter, JsonExporter, MarkdownExporter,
+};
+pub use frontier::{FrontierError, FrontierSearch, DEFAULT_MEMORY_BUDGET};
+pub use import::{CsvImporter, GridImporter, ImportError, Importer, JsonImporter};
+pub use isomorphism::{CanonicalForm, IsomorphismFilter};
+pub use itinerary::{
+    CsvItineraryExporter, Itinerary, JsonItineraryExporter, Stop, TextItineraryExporter,
+};
+pub use limits::{CancellationToken, Limit, SearchLimits, SearchOutcome};
+pub use meetings::MeetingReport;
+pub use observer::{NoObserver, SearchObserver};
+pub use parallel::{ParallelDF2, ParallelState, ParallelStats, SUBTREES_PER_THREAD};
+pub use player_set::{PlayerIter, PlayerSet, WidePlayerSet};
+pub use roster::{Player, Roster, RosterError};
+pub use schedule::{Schedule, ScheduleGrid, Violation};
 use serde::{Deserialize, Serialize};
-use std::convert::TryFrom;
+pub use solutions::{count_solutions, DF2Solutions, SearchStep, SolutionCount, StateSolutions};
+pub use stats::SearchStats;
+use std::sync::Arc;
 use to_explore::ToExplore;
 
 use thiserror::Error;
 
-const ROUND_COUNT: usize = 6;
-const TABLE_COUNT: usize = 6;
-const PLAYERS_PER_TABLE: usize = 4;
-const PLAYER_COUNT: usize = TABLE_COUNT * PLAYERS_PER_TABLE;
-const PLAYER_MASK: u32 = (1 << PLAYER_COUNT) - 1;
-
-const TABLES: [Table; 6] = [
-    Table::Zero,
-    Table::One,
-    Table::Two,
-    Table::Three,
-    Table::Four,
-    Table::Five,
-];
-
-const ROUNDS: [Round; 6] = [
-    Round::Zero,
-    Round::One,
-    Round::Two,
-    Round::Three,
-    Round::Four,
-    Round::Five,
-];
-
-#[derive(Copy, Clone, Debug, PartialOrd, PartialEq)]
-pub enum Table {
-    Zero = 0,
-    One = 1,
-    Two = 2,
-    Three = 3,
-    Four = 4,
-    Five = 5,
-}
-
-impl TryFrom<usize> for Table {
-    type Error = ();
-    fn try_from(value: usize) -> Result<Self, Self::Error> {
-        match value {
-            0 => Ok(Self::Zero),
-            1 => Ok(Self::One),
-            2 => Ok(Self::Two),
-            3 => Ok(Self::Three),
-            4 => Ok(Self::Four),
-            5 => Ok(Self::Five),
-            _ => Err(()),
-        }
-    }
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Round, table and seat counts must all be non-zero")]
+    Empty,
+    #[error("{player_count} players is more than the maximum of {max}")]
+    TooManyPlayers { player_count: usize, max: usize },
+    #[error("At most {max} rounds and {max} tables are supported")]
+    TooManyRoundsOrTables { max: usize },
+    #[error("Round {round} has {table_count} tables but round 0 has {expected}")]
+    UnevenTableCount {
+        round: usize,
+        table_count: usize,
+        expected: usize,
+    },
+    #[error("Round {round} has {seat_count} seats but round 0 has {expected}")]
+    UnevenSeatCount {
+        round: usize,
+        seat_count: usize,
+        expected: usize,
+    },
+    #[error("{player_count} players cannot fill the {seat_count} seats in each round")]
+    TooFewPlayers {
+        player_count: usize,
+        seat_count: usize,
+    },
+    #[error("Only {available} players are available in round {round} to fill {seat_count} seats")]
+    TooFewAvailable {
+        round: usize,
+        available: usize,
+        seat_count: usize,
+    },
+    #[error(transparent)]
+    OutOfRange(#[from] OutOfRange),
+    #[error(transparent)]
+    Pin(#[from] PinError),
+    #[error("Player {player} cannot be paired with themselves")]
+    PairWithSelf { player: usize },
+    #[error("Players {first} and {second} are both forbidden from and required to meet")]
+    ConflictingPair { first: usize, second: usize },
+    #[error("Players must be interchangeable, without absences, pins or pair constraints")]
+    PlayersNotInterchangeable,
 }
 
-#[derive(Copy, Clone, Debug, PartialOrd, PartialEq)]
-pub enum Round {
-    Zero = 0,
-    One = 1,
-    Two = 2,
-    Three = 3,
-    Four = 4,
-    Five = 5,
+#[derive(Debug, Error)]
+pub enum PinError {
+    #[error("Player {player} is pinned but there are only {player_count} players")]
+    UnknownPlayer { player: usize, player_count: usize },
+    #[error("Player {player} is pinned in round {round} but is not available for it")]
+    Unavailable { player: usize, round: usize },
+    #[error("Player {player} is pinned more than once in round {round}")]
+    TwiceInRound { player: usize, round: usize },
+    #[error("Player {player} is pinned to table {table} in more than one round")]
+    TableRevisited { player: usize, table: usize },
+    #[error("Table {table} in round {round} has more players pinned than seats")]
+    TableOverfull { round: usize, table: usize },
+    #[error("Players {first} and {second} are pinned to meet more than once")]
+    MeetTwice { first: usize, second: usize },
+    #[error("Players {first} and {second} are pinned together but may not meet")]
+    ForbiddenPair { first: usize, second: usize },
 }
 
-impl TryFrom<usize> for Round {
-    type Error = ();
-    fn try_from(value: usize) -> Result<Self, Self::Error> {
-        match value {
-            0 => Ok(Self::Zero),
-            1 => Ok(Self::One),
-            2 => Ok(Self::Two),
-            3 => Ok(Self::Three),
-            4 => Ok(Self::Four),
-            5 => Ok(Self::Five),
-            _ => Err(()),
-        }
-    }
+#[derive(Debug, Error)]
+#[error("Index {index} is out of range, there are only {count}")]
+pub struct OutOfRange {
+    pub index: usize,
+    pub count: usize,
 }
 
 #[derive(Debug, Error)]
@@ -103,243 +134,2437 @@ pub struct PlayerNotPlacable {
 
 #[derive(Debug, Error)]
 pub enum InitialisationError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
     #[error(transparent)]
     FinishedStepping(#[from]FinishedStepping),
     #[error(transparent)]
-    PlayerNotPlacable(#[from] PlayerNotPlacable)
+    PlayerNotPlacable(#[from] PlayerNotPlacable),
+    #[error("Player {player} cannot be placed at table {table} in round {round}")]
+    Unplaceable {
+        round: usize,
+        table: usize,
+        player: usize,
+    },
+    #[error("Pinned player {player} is missing from table {table} in round {round}")]
+    PinMissing {
+        round: usize,
+        table: usize,
+        player: usize,
+    },
+    #[error("The schedule breaks the sit-out or must-meet constraints")]
+    ConstraintsBroken,
 }
 
-#[derive(Copy, Clone, Debug)]
-pub struct DF2 {
+#[derive(Debug, Error)]
+#[error("No valid way to continue from this state")]
+pub struct DeadEnd {}
+
+#[derive(Debug, Error)]
+#[error("Rejecting isomorphic states needs a search made with State::new_unordered")]
+pub struct RoundsOrdered {}
+
+#[derive(Debug, Error)]
+pub enum UniqueStepError {
+    #[error(transparent)]
+    DeadEnd(#[from] DeadEnd),
+    #[error(transparent)]
+    RoundsOrdered(#[from] RoundsOrdered),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DF2<S = u32> {
+    config: Config,
     players_placed: u16,
     round: Round,
     table: Table,
     player_number: usize,
-    played_in_round: [u32; ROUND_COUNT],
-    played_on_table_total: [u32; TABLE_COUNT],
-    schedule: [[[u8; 4]; TABLE_COUNT]; ROUND_COUNT],
-    players_played_with: [u32; 32],
+    played_in_round: Vec<S>,
+    played_on_table_total: Vec<S>,
+    schedule: Vec<Vec<Vec<u8>>>,
+    /// Players already tried in the current seat of each table
+    removed: Vec<Vec<S>>,
+    available: Vec<S>,
+    /// Players who must sit out and players who must play in each round, set on entering it
+    forced: Vec<(S, S)>,
+    /// Whether every must-meet pair could still meet on entering each round
+    pairs_can_meet: Vec<bool>,
+    /// Has an extra entry for the empty seat marker which is always kept empty
+    players_played_with: Vec<S>,
+    /// Players each player may never share a table with, with an entry for the empty seat marker
+    forbidden: Vec<S>,
+    /// Round 0 is filled in order and never revisited, as players are interchangeable
+    round_zero_fixed: bool,
+    /// Whether rounds and tables are interchangeable too, so `symmetry_mask` applies
+    symmetric: bool,
+    /// Players placed before the search was confined to a subtree, which are never removed
+    fixed_placed: u16,
 }
 
-impl DF2 {
-    pub fn new() -> Self {
+impl<S: PlayerSet> DF2<S> {
+    pub fn new(config: Config) -> Result<Self, ConfigError> {
+        config.check_capacity::<S>()?;
+        let player_count = config.player_count();
+        let seat_count = config.seat_count();
+        let schedule = config
+            .rounds()
+            .map(|round| {
+                config
+                    .tables()
+                    .map(|table| vec![player_count as u8; config.seats(round, table)])
+                    .collect()
+            })
+            .collect();
         let mut new = Self {
             players_placed: 0,
-            round: Round::Zero,
-            table: Table::Zero,
+            round: Round::ZERO,
+            table: Table::ZERO,
             player_number: 0,
-            played_in_round: [0; ROUND_COUNT],
-            played_on_table_total: [0; ROUND_COUNT],
-            schedule: [[[PLAYER_COUNT as u8; 4]; ROUND_COUNT]; TABLE_COUNT],
-            players_played_with: [0; 32],
+            played_in_round: vec![S::empty(); config.round_count()],
+            played_on_table_total: vec![S::empty(); config.table_count()],
+            schedule,
+            removed: vec![vec![S::empty(); config.table_count()]; config.round_count()],
+            available: config
+                .rounds()
+                .map(|round| config.available(round))
+                .collect(),
+            forced: vec![(S::empty(), S::empty()); config.round_count()],
+            pairs_can_meet: vec![true; config.round_count()],
+            players_played_with: vec![S::empty(); player_count + 1],
+            forbidden: (0..=player_count)
+                .map(|player| config.forbidden_with(player))
+                .collect(),
+            round_zero_fixed: config.players_interchangeable(),
+            symmetric: config.rounds_interchangeable() && config.uniform_table_size().is_some(),
+            fixed_placed: 0,
+            config,
+        };
+        for round in new.config.rounds() {
+            for table in new.config.tables() {
+                for seat in 0..new.config.pinned(round, table).len() {
+                    new.round = round;
+                    new.table = table;
+                    new.player_number = seat;
+                    let player = new.config.pinned(round, table)[seat] as u8;
+                    new.schedule[round.index()][table.index()][seat] = player;
+                    new.players_placed += 1;
+                    new.toggle_player(player);
+                }
+            }
+        }
+        new.round = Round::ZERO;
+        new.table = Table::ZERO;
+        new.player_number = new.config.pinned(Round::ZERO, Table::ZERO).len();
+        new.enter_round(Round::ZERO);
+        if new.skip_filled_tables().is_err() {
+            // Every seat is pinned
+            new.round = new.config.round(new.config.round_count() - 1).unwrap();
+            new.table = new.config.last_table();
+            new.player_number = new.config.seats(new.round, new.table) - 1;
+        }
+        if new.round_zero_fixed {
+            // Any schedule can be relabelled so that round 0 is filled in order
+            for seat in 0..seat_count {
+                if seat != 0 {
+                    new.increment().unwrap();
+                }
+                let player = new.get_mask(new.round, new.table).lowest().unwrap();
+                new.apply_player(player as u8);
+            }
+            // Backtracking into round 0 would only try relabellings of it
+            new.fixed_placed = new.players_placed;
+        }
+
+        Ok(new)
+    }
+    pub fn from_slice(config: Config, players: &[u8]) -> Result<Self, InitialisationError> {
+        let mut df = Self::new(config)?;
+        for player in players.iter() {
+            if df.last_player() != df.empty_seat() {
+                df.increment()?;
+            }
+            if *player as usize >= df.config.player_count()
+                || !df.get_mask(df.round, df.table).contains(*player as usize)
+            {
+                Err(PlayerNotPlacable{})?;
+            }
+            df.apply_player(*player);
+        }
+
+        Ok(df)
+    }
+    /// Continues from a schedule, which may be partly filled as long as its players fill the seats
+    /// in search order. Only schedules the search could reach are accepted, so round 0 must be
+    /// in order when players are interchangeable
+    pub fn from_schedule(config: Config, schedule: &Schedule) -> Result<Self, InitialisationError> {
+        let mut df = Self::new(config)?;
+        let config = df.config.clone();
+        let prefilled = df.schedule.clone();
+        let empty: Vec<u8> = Vec::new();
+        for round in config.rounds() {
+            for table in config.tables() {
+                let seated = schedule
+                    .rounds()
+                    .get(round.index())
+                    .and_then(|tables| tables.get(table.index()))
+                    .unwrap_or(&empty);
+                let fixed = &prefilled[round.index()][table.index()];
+                let unplaceable = |player: u8| InitialisationError::Unplaceable {
+                    round: round.index(),
+                    table: table.index(),
+                    player: player as usize,
+                };
+                if let Some(&player) = fixed
+                    .iter()
+                    .find(|&&player| player != df.empty_seat() && !seated.contains(&player))
+                {
+                    return Err(
+                        if config.pinned(round, table).contains(&(player as usize)) {
+                            InitialisationError::PinMissing {
+                                round: round.index(),
+                                table: table.index(),
+                                player: player as usize,
+                            }
+                        } else {
+                            unplaceable(player)
+                        },
+                    );
+                }
+                let mut rest: Vec<u8> = seated
+                    .iter()
+                    .copied()
+                    .filter(|player| !fixed.contains(player))
+                    .collect();
+                rest.sort_unstable();
+                for player in rest {
+                    if df.last_player() != df.empty_seat() {
+                        df.increment().map_err(|_| unplaceable(player))?;
+                    }
+                    if df.round != round
+                        || df.table != table
+                        || player >= df.empty_seat()
+                        || !df.get_mask(df.round, df.table).contains(player as usize)
+                    {
+                        return Err(unplaceable(player));
+                    }
+                    df.apply_player(player);
+                }
+            }
+        }
+        Ok(df)
+    }
+    pub const fn get_config(&self) -> &Config {
+        &self.config
+    }
+    fn empty_seat(&self) -> u8 {
+        self.config.player_count() as u8
+    }
+    fn toggle_player(&mut self, player: u8) {
+        assert!(player < self.empty_seat());
+        let round = self.round;
+        let table = self.table;
+        let player_mask = S::single(player as usize);
+        self.played_in_round[round.index()] ^= player_mask;
+        self.played_on_table_total[table.index()] ^= player_mask;
+        for &other in self.schedule[round.index()][table.index()].iter() {
+            if other != player && other != self.empty_seat() {
+                self.players_played_with[other as usize] ^= player_mask;
+                self.players_played_with[player as usize] ^= S::single(other as usize);
+            }
+        }
+    }
+    fn apply_player(&mut self, player: u8) {
+        assert!(player < self.empty_seat());
+        debug_assert!(self
+            .get_mask(self.round, self.table)
+            .contains(player as usize));
+        self.schedule[self.round.index()][self.table.index()][self.player_number] = player;
+        self.players_placed += 1;
+        log::trace!(
+            "placing player {} into {:?}",
+            player,
+            self.schedule[self.round.index()][self.table.index()]
+        );
+        self.toggle_player(player);
+    }
+    fn last_player(&self) -> u8 {
+        self.schedule[self.round.index()][self.table.index()][self.player_number]
+    }
+    fn remove_last_player(&mut self) {
+        let player = self.last_player();
+        assert!(player < self.empty_seat());
+        log::trace!(
+            "removing player {} from {:?}",
+            player,
+            self.schedule[self.round.index()][self.table.index()]
+        );
+        self.schedule[self.round.index()][self.table.index()][self.player_number] =
+            self.empty_seat();
+        self.players_placed -= 1;
+        self.toggle_player(player);
+    }
+    fn get_mask(&self, round: Round, table: Table) -> S {
+        let mut mask = self.available[round.index()]
+            & !self.removed[round.index()][table.index()]
+            & !self.played_in_round[round.index()]
+            & !self.played_on_table_total[table.index()];
+        if !self.pairs_can_meet[round.index()] {
+            return S::empty();
+        }
+        for &player in self.schedule[round.index()][table.index()].iter() {
+            mask &= !(self.players_played_with[player as usize] | self.forbidden[player as usize]);
+        }
+        let (must_sit_out, must_play) = self.forced[round.index()];
+        mask &= !must_sit_out;
+        let played = self.played_in_round[round.index()];
+        let must_play = must_play & !played;
+        let seats_left = self.config.seat_count() - played.count() as usize;
+        match (must_play.count() as usize).cmp(&seats_left) {
+            core::cmp::Ordering::Less => {}
+            core::cmp::Ordering::Equal => mask &= must_play,
+            core::cmp::Ordering::Greater => mask = S::empty(),
+        }
+        let seated = &self.schedule[round.index()][table.index()];
+        let seat = seated
+            .iter()
+            .take_while(|&&player| player != self.empty_seat())
+            .count();
+        if seat > self.config.pinned(round, table).len() {
+            // Players at a table are seated in ascending order, after any pinned players
+            mask &= !S::up_to(seated[seat - 1] as usize);
+        }
+        if self.symmetric {
+            mask &= self.symmetry_mask(round, table, seat);
+        }
+        mask
+    }
+    /// Restricts the search to one schedule out of those equivalent under relabelling players,
+    /// reordering rounds and reordering tables, given that round 0 is filled in order.
+    ///
+    /// Every schedule can be brought into this form: reorder tables 1.. (moving their round 0
+    /// players with them) so that round 1 table 0 takes one player from each of round 0 tables
+    /// 1..=P, relabel the players of each round 0 table in the order of their table in round 1,
+    /// then sort rounds 2.. by the lowest player at table 0, which differ as nobody visits a
+    /// table twice
+    fn symmetry_mask(&self, round: Round, table: Table, seat: usize) -> S {
+        let table_size = self.config.seats(Round::ZERO, Table::ZERO);
+        match round.index() {
+            1 if table == Table::ZERO => {
+                let player = (seat + 1) * table_size;
+                if player < self.config.player_count() {
+                    S::single(player)
+                } else {
+                    S::empty()
+                }
+            }
+            1 => {
+                // Only the lowest player of each round 0 table yet to be placed in round 1
+                let unplaced = !self.played_in_round[1];
+                let mut mask = S::empty();
+                for start in (0..self.config.player_count()).step_by(table_size) {
+                    let group = S::first_n(start + table_size) & !S::first_n(start);
+                    if let Some(player) = (group & unplaced).lowest() {
+                        mask.insert(player);
+                    }
+                }
+                mask
+            }
+            round if round >= 3 && table == Table::ZERO && seat == 0 => {
+                !S::up_to(self.schedule[round - 1][0][0] as usize)
+            }
+            _ => !S::empty(),
+        }
+    }
+    /// Works out from the rounds before it whether must-meet pairs can still meet, and who is
+    /// forced to sit out or play in a round
+    fn enter_round(&mut self, round: Round) {
+        self.pairs_can_meet[round.index()] = self
+            .config
+            .must_meet_pairs()
+            .iter()
+            .all(|&(first, second)| self.can_still_meet(first, second, round));
+        let available = self.available[round.index()];
+        let sit_out_count = self.config.sit_out_count(round);
+        if sit_out_count == 0 {
+            self.forced[round.index()] = (S::empty(), available);
+            return;
+        }
+        let mut sit_outs = sit_out::SitOuts::new(self.config.player_count());
+        for &played in self.played_in_round[..round.index()].iter() {
+            sit_outs.record(played);
+        }
+        self.forced[round.index()] = sit_outs.forced(available, sit_out_count);
+    }
+    /// Whether two players have met, or could still meet at a table in `from` or a later round.
+    /// Those rounds are empty apart from pinned players
+    fn can_still_meet(&self, first: usize, second: usize, from: Round) -> bool {
+        if self.players_played_with[first].contains(second) {
+            return true;
+        }
+        self.config.rounds().skip(from.index()).any(|round| {
+            self.config.tables().any(|table| {
+                let seated = &self.schedule[round.index()][table.index()];
+                let mut free_seats = seated
+                    .iter()
+                    .filter(|&&player| player == self.empty_seat())
+                    .count();
+                [first, second].iter().all(|&player| {
+                    if seated.contains(&(player as u8)) {
+                        return true;
+                    }
+                    let excluded = self.players_played_with[player] | self.forbidden[player];
+                    let can_join = free_seats > 0
+                        && self.available[round.index()].contains(player)
+                        && !self.played_in_round[round.index()].contains(player)
+                        && !self.played_on_table_total[table.index()].contains(player)
+                        && seated
+                            .iter()
+                            .filter(|&&other| other != self.empty_seat())
+                            .all(|&other| !excluded.contains(other as usize));
+                    free_seats = free_seats.saturating_sub(1);
+                    can_join
+                })
+            })
+        })
+    }
+    fn must_meet_pairs_met(&self) -> bool {
+        self.config
+            .must_meet_pairs()
+            .iter()
+            .all(|&(first, second)| self.players_played_with[first].contains(second))
+    }
+    /// Number of different tables a player has been seated at, which is less than the number of
+    /// tables for players who sit out or miss rounds
+    pub fn tables_visited(&self, player: usize) -> usize {
+        self.played_on_table_total
+            .iter()
+            .filter(|played| played.contains(player))
+            .count()
+    }
+    pub const fn get_players_placed(&self) -> u16 {
+        self.players_placed
+    }
+    fn increment(&mut self) -> Result<(), FinishedStepping> {
+        self.player_number += 1;
+        self.skip_filled_tables()?;
+        self.removed[self.round.index()][self.table.index()] = S::empty();
+        Ok(())
+    }
+    /// Moves the cursor on from the end of a table, past any tables filled by pinned players.
+    /// Pinned players take the first seats of a table, so the cursor never lands on them
+    fn skip_filled_tables(&mut self) -> Result<(), FinishedStepping> {
+        let start = (self.round, self.table, self.player_number);
+        while self.player_number >= self.config.seats(self.round, self.table) {
+            if let Ok(table) = self.config.table(self.table.index() + 1) {
+                self.table = table;
+            } else if let Ok(round) = self.config.round(self.round.index() + 1) {
+                self.enter_round(round);
+                self.round = round;
+                self.table = Table::ZERO;
+            } else {
+                let (round, table, player_number) = start;
+                self.round = round;
+                self.table = table;
+                self.player_number = player_number - 1;
+                return Err(FinishedStepping {  });
+            }
+            self.player_number = self.config.pinned(self.round, self.table).len();
+        }
+        Ok(())
+    }
+    fn decrement(&mut self) -> Result<(), ExceededMaxBacktrack> {
+        if self.player_number == self.config.pinned(self.round, self.table).len() {
+            self.removed[self.round.index()][self.table.index()] = S::empty();
+            let start = (self.round, self.table);
+            loop {
+                if let Ok(table) = self.config.table(self.table.index().wrapping_sub(1)) {
+                    self.table = table;
+                } else if let Some(round) = self
+                    .config
+                    .round(self.round.index().wrapping_sub(1))
+                    .ok()
+                    .filter(|&round| round != Round::ZERO || !self.round_zero_fixed)
+                {
+                    self.round = round;
+                    self.table = self.config.last_table();
+                } else {
+                    let (round, table) = start;
+                    self.round = round;
+                    self.table = table;
+                    return Err(ExceededMaxBacktrack{});
+                }
+                if self.config.pinned(self.round, self.table).len()
+                    < self.config.seats(self.round, self.table)
+                {
+                    break;
+                }
+            }
+            self.player_number = self.config.seats(self.round, self.table) - 1;
+        } else {
+            self.player_number -= 1
+        }
+        Ok(())
+    }
+    /// Removes the most recently placed player, so that the next step tries the following
+    /// candidate for the same seat
+    pub fn backtrack(&mut self) -> Result<(), ExceededMaxBacktrack> {
+        self.backtrack_observed(&mut NoObserver)
+    }
+    /// Like `backtrack`, telling `observer` about the removal
+    pub fn backtrack_observed<O: SearchObserver>(
+        &mut self,
+        observer: &mut O,
+    ) -> Result<(), ExceededMaxBacktrack> {
+        if self.players_placed <= self.fixed_placed {
+            return Err(ExceededMaxBacktrack {});
+        }
+        if self.last_player() == self.empty_seat() {
+            self.decrement()?;
+        }
+        let player = self.last_player();
+        assert_ne!(player, self.empty_seat());
+        self.remove_last_player();
+        observer.removed(self.round, self.table, player as usize);
+        observer.backtracked(self.players_placed as usize);
+        // Candidates are tried in order, so everyone up to the removed player has been tried
+        self.removed[self.round.index()][self.table.index()] = S::up_to(player as usize);
+        Ok(())
+    }
+    pub fn step(&mut self) -> Result<(), StepError> {
+        self.step_observed(&mut NoObserver)
+    }
+    /// Like `step`, telling `observer` about each placement, removal and dead end
+    pub fn step_observed<O: SearchObserver>(&mut self, observer: &mut O) -> Result<(), StepError> {
+        if self.last_player() != self.empty_seat() {
+            if let Err(finished) = self.increment() {
+                if self.must_meet_pairs_met() {
+                    return Err(finished.into());
+                }
+                observer.dead_end(self.players_placed as usize);
+                self.backtrack_observed(observer)?;
+            }
+        }
+
+        let mut mask = self.get_mask(self.round, self.table);
+        while mask.is_empty() {
+            observer.dead_end(self.players_placed as usize);
+            self.backtrack_observed(observer)?;
+            mask = self.get_mask(self.round, self.table);
+        }
+        let player = mask.lowest().unwrap() as u8;
+        self.apply_player(player);
+        observer.placed(self.round, self.table, player as usize);
+        Ok(())
+    }
+    pub fn get_schedule(&self) -> Vec<Vec<Vec<u8>>> {
+        self.schedule.clone()
+    }
+    /// The players placed so far, leaving out empty seats so a partial search only shows real
+    /// players
+    pub fn schedule(&self) -> Schedule {
+        let mut rounds = self.get_schedule();
+        for players in rounds.iter_mut().flatten() {
+            players.retain(|&player| player != self.empty_seat());
+        }
+        Schedule::new(rounds)
+    }
+    /// Whether the cursor and every array match the config, for checking searches read from a
+    /// checkpoint
+    pub(crate) fn fits_config(&self) -> bool {
+        let config = &self.config;
+        let round_count = config.round_count();
+        let table_count = config.table_count();
+        let player_count = config.player_count();
+        let fits_round = |(round, tables): (Round, &Vec<Vec<u8>>)| {
+            tables.len() == table_count
+                && config.tables().zip(tables.iter()).all(|(table, players)| {
+                    players.len() == config.seats(round, table)
+                        && players.iter().all(|&player| player <= self.empty_seat())
+                })
+        };
+        let fits_grid = |grid: &Vec<Vec<S>>| {
+            grid.len() == round_count && grid.iter().all(|tables| tables.len() == table_count)
+        };
+        config.check_capacity::<S>().is_ok()
+            && self.round.index() < round_count
+            && self.table.index() < table_count
+            && self.player_number < config.seats(self.round, self.table)
+            && self.schedule.len() == round_count
+            && config.rounds().zip(self.schedule.iter()).all(fits_round)
+            && self.played_in_round.len() == round_count
+            && self.played_on_table_total.len() == table_count
+            && fits_grid(&self.removed)
+            && self.available.len() == round_count
+            && self.forced.len() == round_count
+            && self.pairs_can_meet.len() == round_count
+            && self.players_played_with.len() == player_count + 1
+            && self.forbidden.len() == player_count + 1
+            && self.fits_derived_fields()
+    }
+    /// Whether the fields `new` works out from the config still agree with it, and the players
+    /// counted as placed are those in the schedule
+    fn fits_derived_fields(&self) -> bool {
+        let config = &self.config;
+        let round_zero_fixed = config.players_interchangeable();
+        let min_fixed = if round_zero_fixed {
+            config.seat_count()
+        } else {
+            0
+        };
+        let placed = self
+            .schedule
+            .iter()
+            .flatten()
+            .flatten()
+            .filter(|&&player| player != self.empty_seat())
+            .count();
+        self.round_zero_fixed == round_zero_fixed
+            && self.symmetric
+                == (config.rounds_interchangeable() && config.uniform_table_size().is_some())
+            && config
+                .rounds()
+                .zip(self.available.iter())
+                .all(|(round, &available)| available == config.available(round))
+            && self
+                .forbidden
+                .iter()
+                .enumerate()
+                .all(|(player, &forbidden)| forbidden == config.forbidden_with(player))
+            && placed == self.players_placed as usize
+            && (min_fixed..=placed).contains(&(self.fixed_placed as usize))
+            && self.fits_player_sets()
+    }
+    /// Whether the player sets are those placing the schedule's players one seat at a time
+    /// gives, and the others only hold players in the config
+    fn fits_player_sets(&self) -> bool {
+        let config = &self.config;
+        let players = S::first_n(config.player_count());
+        let mut played_in_round = vec![S::empty(); config.round_count()];
+        let mut played_on_table_total = vec![S::empty(); config.table_count()];
+        let mut players_played_with = vec![S::empty(); config.player_count() + 1];
+        for (round, tables) in self.schedule.iter().enumerate() {
+            for (table, seated) in tables.iter().enumerate() {
+                for (seat, &player) in seated.iter().enumerate() {
+                    if player == self.empty_seat() {
+                        continue;
+                    }
+                    let player_mask = S::single(player as usize);
+                    played_in_round[round] ^= player_mask;
+                    played_on_table_total[table] ^= player_mask;
+                    for &other in seated[..seat].iter() {
+                        if other != player && other != self.empty_seat() {
+                            players_played_with[other as usize] ^= player_mask;
+                            players_played_with[player as usize] ^= S::single(other as usize);
+                        }
+                    }
+                }
+            }
+        }
+        played_in_round == self.played_in_round
+            && played_on_table_total == self.played_on_table_total
+            && players_played_with == self.players_played_with
+            && self
+                .removed
+                .iter()
+                .flatten()
+                .all(|&removed| (removed & !players).is_empty())
+            && self.forced.iter().all(|&(must_sit_out, must_play)| {
+                ((must_sit_out | must_play) & !players).is_empty()
+            })
+    }
+    /// Confines the search to schedules starting with the players placed so far, so that it
+    /// finishes rather than backtracking past them. Searches from different prefixes made with
+    /// `from_slice` then cover separate parts of the search
+    pub fn subtree(mut self) -> Self {
+        self.fixed_placed = self.players_placed;
+        self
+    }
+    /// Every schedule the search can still reach, continuing from the current position
+    pub fn solutions(self) -> DF2Solutions<S> {
+        DF2Solutions::new(self)
+    }
+    /// The grid written by `State::format_schedule`, with empty seats shown as `.` and the seat
+    /// at the cursor in brackets
+    pub fn format_schedule<W: core::fmt::Write>(&self, output: &mut W) -> core::fmt::Result {
+        let max_seats: Vec<usize> = self
+            .config
+            .rounds()
+            .map(|round| self.config.max_seats(round))
+            .collect();
+        let cursor = (self.round.index(), self.table.index(), self.player_number);
+        export::write_grid(
+            output,
+            &self.schedule,
+            self.config.table_count(),
+            &max_seats,
+            |round, table, seat, player| {
+                let text = if player == self.empty_seat() {
+                    ".".to_string()
+                } else {
+                    player.to_string()
+                };
+                if (round, table, seat) == cursor {
+                    format!("[{}]", text)
+                } else {
+                    text
+                }
+            },
+        )
+    }
+}
+
+impl<S: PlayerSet> std::fmt::Display for DF2<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.format_schedule(f)
+    }
+}
+
+impl<S: PlayerSet> Default for DF2<S> {
+    fn default() -> Self {
+        Self::new(Config::default()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init() {
+        let _ = env_logger::builder()
+            .filter_level(log::LevelFilter::Trace)
+            .is_test(true)
+            .try_init();
+    }
+
+    fn round(index: usize) -> Round {
+        Config::default().round(index).unwrap()
+    }
+
+    fn table(index: usize) -> Table {
+        Config::default().table(index).unwrap()
+    }
+
+    fn expand_bitvec(mut x: u32) -> Vec<u8> {
+        let mut out = Vec::new();
+        while x != 0 {
+            let zeros = x.trailing_zeros() as u8;
+            x ^= 1 << zeros;
+            out.push(zeros);
+        }
+
+        out
+    }
+
+    #[test]
+    fn test() {
+        let state: DF2 = DF2::default();
+        let mask = expand_bitvec(state.get_mask(round(1), table(0)));
+        assert_eq!(mask, vec![4]);
+    }
+    #[test]
+    fn test2() {
+        let state: DF2 = DF2::from_slice(Config::default(), &[4, 8, 12, 16]).unwrap();
+        let mask = expand_bitvec(state.get_mask(round(1), table(1)));
+        assert_eq!(mask, vec![0, 9, 13, 17, 20]);
+    }
+    #[test]
+    fn test3() {
+        let state: DF2 = DF2::from_slice(Config::default(), &[4, 8, 12, 16, 0, 9, 13, 17]).unwrap();
+        let mask = expand_bitvec(state.get_mask(round(1), table(2)));
+        assert_eq!(mask, vec![1, 5, 14, 18, 20]);
+    }
+    #[test]
+    fn test4() {
+        let state: DF2 = DF2::from_slice(
+            Config::default(),
+            &[4, 8, 12, 16, 0, 9, 13, 17, 1, 5, 14, 18, 20],
+        )
+        .unwrap();
+        let mask = expand_bitvec(state.get_mask(round(1), table(2)));
+        assert_eq!(mask, vec![21]);
+    }
+    #[test]
+    fn run_successful() {
+        init();
+        let state: DF2 = DF2::from_slice(
+            Config::default(),
+            &[
+                4, 8, 12, 16, 0, 9, 13, 20, 1, 5, 17, 21, 2, 6, 18, 22, 3, 10, 14, 23, 7, 11, 15,
+                19, 5, 9, 14, 18, 3, 15, 16, 22, 2, 7, 12, 20, 1, 8, 19, 23, 6, 11, 13, 21, 0, 4,
+                10, 17, 6, 10, 19, 20, 2, 11, 14, 17, 4, 15, 18, 23, 0, 7, 16, 21, 1, 9, 12, 22, 3,
+                5, 8, 13, 7, 13, 17, 23, 10, 12, 18, 21, 0, 14, 19, 22, 3, 4, 11,
+            ],
+        )
+        .unwrap();
+        let mask = expand_bitvec(state.get_mask(state.round, state.table));
+        assert_eq!(mask, vec![20]);
+    }
+    #[test]
+    fn symmetric_search_finds_solutions() {
+        for &(rounds, tables, seats) in [(3, 4, 3), (4, 4, 3), (5, 5, 4)].iter() {
+            let config = Config::new(rounds, tables, seats).unwrap();
+            let schedule = run_df2::<u32>(&config);
+            assert_valid(&config, &schedule);
+            let table_0: Vec<u8> = (1..=seats as u8).map(|i| i * seats as u8).collect();
+            assert_eq!(schedule[1][0], table_0);
+        }
+        // Round 0 is never revisited, so a search with no solution ends there
+        let mut state: DF2 = DF2::new(Config::new(4, 4, 4).unwrap()).unwrap();
+        let finished = loop {
+            match state.step() {
+                Ok(()) => assert!(state.round != Round::ZERO),
+                Err(err) => break err,
+            }
+        };
+        assert!(matches!(finished, StepError::ExceededMaxBacktrack(_)));
+        let mut fresh: DF2 = DF2::default();
+        assert!(fresh.backtrack().is_err());
+        assert_eq!(fresh.get_players_placed(), 24);
+    }
+    /// Canonical forms of every finished schedule, and the number of states explored
+    fn all_state_solutions(
+        config: &Config,
+        mut filter: Option<&mut IsomorphismFilter>,
+    ) -> (std::collections::HashSet<CanonicalForm>, usize) {
+        let forms = IsomorphismFilter::new(config).unwrap();
+        let mut solutions = std::collections::HashSet::new();
+        let mut explored = 0;
+        let mut stack: Vec<State> = vec![State::new_unordered(config.clone()).unwrap()];
+        let mut state2 = stack[0].clone();
+        while let Some(mut state) = stack.pop() {
+            explored += 1;
+            let result = match filter.as_mut() {
+                Some(filter) => state.step_unique(&mut state2, filter),
+                None => state.step(&mut state2).map_err(UniqueStepError::from),
+            };
+            match result {
+                Ok(Some(())) => {
+                    stack.push(state);
+                    stack.push(state2.clone());
+                }
+                Ok(None) => {
+                    solutions.insert(forms.canonical_form(&state));
+                }
+                Err(UniqueStepError::DeadEnd(_)) => {}
+                Err(error) => panic!("{}", error),
+            }
+        }
+        (solutions, explored)
+    }
+    #[test]
+    fn isomorphism_rejection() {
+        let config = Config::new(3, 4, 2).unwrap();
+        let (all, explored_all) = all_state_solutions(&config, None);
+        let mut filter = IsomorphismFilter::new(&config).unwrap();
+        let (unique, explored_unique) = all_state_solutions(&config, Some(&mut filter));
+        assert_eq!(unique, all);
+        assert!(explored_unique < explored_all);
+
+        // DF2 finds one schedule from every class, as its symmetry breaking keeps one of each
+        let mut df2_solutions = std::collections::HashSet::new();
+        let mut state: DF2 = DF2::new(config.clone()).unwrap();
+        loop {
+            match state.step() {
+                Ok(()) => {}
+                Err(StepError::FinishedStepping(_)) => {
+                    df2_solutions.insert(filter.canonical_schedule(&state.get_schedule()));
+                    state.backtrack().unwrap();
+                }
+                Err(StepError::ExceededMaxBacktrack(_)) => break,
+            }
+        }
+        assert_eq!(df2_solutions, all);
+
+        // Relabelling players and reversing rounds and tables leaves the form as it was
+        let df2 = DF2::<u32>::new(config.clone()).unwrap();
+        let schedule = df2.solutions().last().unwrap();
+        let mut relabelled = schedule.rounds().to_vec();
+        relabelled.reverse();
+        for tables in relabelled.iter_mut() {
+            tables.reverse();
+            for player in tables.iter_mut().flatten() {
+                *player = 7 - *player;
+            }
+        }
+        assert_eq!(
+            filter.canonical_schedule(schedule.rounds()),
+            filter.canonical_schedule(&relabelled)
+        );
+        // Only rounds and tables that cannot be told apart are tried in different orders, so
+        // the 6 round and 6 table default tries a few dozen rather than every 6! * 6!
+        let default: State = State::new_unordered(Config::default()).unwrap();
+        let default_filter = IsomorphismFilter::new(&Config::default()).unwrap();
+        let orders = default_filter.orders_tried(&default);
+        assert!(orders <= 36, "tried {} orders", orders);
+
+        // A search keeping rounds in order is turned away rather than losing solutions
+        let mut ordered: State = State::new(config.clone()).unwrap();
+        let mut state2 = ordered.clone();
+        assert!(matches!(
+            ordered.step_unique(&mut state2, &mut filter),
+            Err(UniqueStepError::RoundsOrdered(_))
+        ));
+        assert!(ordered.bstep_unique(&mut |_| {}, &mut filter).is_err());
+
+        let pinned = config.with_pin(0, 1, 1).unwrap();
+        assert!(IsomorphismFilter::new(&pinned).is_err());
+    }
+    #[test]
+    fn enumerate_solutions() {
+        let config = Config::new(3, 4, 2).unwrap();
+        let df2: DF2 = DF2::new(config.clone()).unwrap();
+        let schedules: Vec<_> = df2.solutions().collect();
+        for schedule in schedules.iter() {
+            assert_valid(&config, schedule.rounds());
+        }
+        let distinct: std::collections::HashSet<_> = schedules.iter().collect();
+        assert_eq!(distinct.len(), schedules.len());
+
+        let filter = IsomorphismFilter::new(&config).unwrap();
+        let df2_count = count_solutions(schedules, Some(filter));
+        assert_eq!(df2_count.unique, Some(3));
+
+        let state: State = State::new(config.clone()).unwrap();
+        let state_count = count_solutions(state.clone().solutions(), None);
+        assert_eq!(state_count.unique, None);
+        for schedule in state.solutions() {
+            assert_valid(&config, schedule.rounds());
+        }
+        let state: State = State::new_unordered(config.clone()).unwrap();
+        let filter = IsomorphismFilter::new(&config).unwrap();
+        let unordered_count = count_solutions(state.solutions(), Some(filter));
+        assert_eq!(unordered_count.unique, Some(3));
+        assert!(unordered_count.solutions >= state_count.solutions);
+
+        // A search with no solution yields nothing
+        let df2: DF2 = DF2::new(Config::new(4, 4, 4).unwrap()).unwrap();
+        assert_eq!(df2.solutions().count(), 0);
+    }
+    #[test]
+    fn schedule_validation() {
+        let config = Config::new(4, 4, 3)
+            .unwrap()
+            .with_player_count(14)
+            .unwrap()
+            .with_forbidden_pair(0, 8)
+            .unwrap()
+            .with_must_meet_pair(1, 9)
+            .unwrap();
+        let df2: DF2 = DF2::new(config.clone()).unwrap();
+        let schedule = df2.solutions().next().unwrap();
+        assert_eq!(schedule.validate(&config), vec![]);
+        let state: State = State::new(config.clone()).unwrap();
+        for schedule in state.solutions().take(5) {
+            assert!(schedule.is_valid(&config));
+        }
+
+        let hand_written = Schedule::new(vec![
+            vec![vec![0, 1, 2], vec![3, 4, 5], vec![6, 7, 8], vec![9, 10, 11]],
+            vec![
+                vec![3, 6, 9],
+                vec![0, 8, 10],
+                vec![1, 12, 1],
+                vec![2, 4, 14],
+            ],
+            vec![vec![0, 1, 5], vec![2, 6]],
+        ]);
+        assert_eq!(
+            hand_written.validate(&config),
+            vec![
+                Violation::WrongRoundCount {
+                    round_count: 3,
+                    expected: 4
+                },
+                Violation::ForbiddenPair {
+                    first: 0,
+                    second: 8,
+                    round: 1,
+                    table: 1
+                },
+                Violation::TwiceInRound {
+                    player: 1,
+                    round: 1,
+                    first_table: 2,
+                    second_table: 2
+                },
+                Violation::UnknownPlayer {
+                    round: 1,
+                    table: 3,
+                    seat: 2,
+                    player: 14
+                },
+                Violation::UnfairSitOuts { round: 1 },
+                Violation::WrongTableCount {
+                    round: 2,
+                    table_count: 2,
+                    expected: 4
+                },
+                Violation::TableRevisited {
+                    player: 0,
+                    table: 0,
+                    first_round: 0,
+                    second_round: 2
+                },
+                Violation::TableRevisited {
+                    player: 1,
+                    table: 0,
+                    first_round: 0,
+                    second_round: 2
+                },
+                Violation::RepeatMeeting {
+                    first: 0,
+                    second: 1,
+                    round: 2,
+                    table: 0,
+                    earlier_round: 0
+                },
+                Violation::WrongTableSize {
+                    round: 2,
+                    table: 1,
+                    seats: 2,
+                    expected: 3
+                },
+                Violation::UnfairSitOuts { round: 2 },
+                Violation::PairNeverMet {
+                    first: 1,
+                    second: 9
+                },
+            ]
+        );
+
+        let config = Config::new(1, 2, 2)
+            .unwrap()
+            .with_player_count(4)
+            .unwrap()
+            .with_pin(3, 0, 1)
+            .unwrap();
+        let schedule = Schedule::new(vec![vec![vec![0, 3], vec![1]]]);
+        assert_eq!(
+            schedule.validate(&config),
+            vec![
+                Violation::WrongTableSize {
+                    round: 0,
+                    table: 1,
+                    seats: 1,
+                    expected: 2
+                },
+                Violation::PinIgnored {
+                    player: 3,
+                    round: 0,
+                    table: 1
+                },
+                Violation::MissingPlayer {
+                    player: 2,
+                    round: 0
+                },
+            ]
+        );
+    }
+    #[test]
+    fn export_formats() {
+        let schedule = Schedule::new(vec![
+            vec![vec![0, 1], vec![2, 3]],
+            vec![vec![2, 0], vec![1, 3]],
+        ]);
+        assert_eq!(
+            CsvExporter::default().export_string(&schedule).unwrap(),
+            "round,table,seat,player\n0,0,0,0\n0,0,1,1\n0,1,0,2\n0,1,1,3\n\
+             1,0,0,2\n1,0,1,0\n1,1,0,1\n1,1,1,3\n"
+        );
+        assert_eq!(
+            JsonExporter::default().export_string(&schedule).unwrap(),
+            "{\"rounds\":[[[0,1],[2,3]],[[2,0],[1,3]]]}\n"
+        );
+        assert_eq!(
+            MarkdownExporter::default()
+                .export_string(&schedule)
+                .unwrap(),
+            "| Round | Table 1 | Table 2 |\n| ---: | --- | --- |\n\
+             | 1 | 0, 1 | 2, 3 |\n| 2 | 2, 0 | 1, 3 |\n"
+        );
+
+        // Every exporter can be used in place of another
+        let config = Config::new(3, 4, 3).unwrap();
+        let df2: DF2 = DF2::new(config.clone()).unwrap();
+        let schedule = df2.solutions().next().unwrap();
+        let exporters: Vec<Box<dyn Exporter>> = vec![
+            Box::new(CsvExporter::default()),
+            Box::new(JsonExporter {
+                pretty: true,
+                roster: None,
+            }),
+            Box::new(MarkdownExporter::default()),
+            Box::new(GridExporter::default()),
+        ];
+        for exporter in exporters.iter() {
+            let mut output = Vec::new();
+            exporter.export(&schedule, &mut output).unwrap();
+            assert!(!output.is_empty());
+        }
+        let json = JsonExporter::default().export_string(&schedule).unwrap();
+        assert_eq!(serde_json::from_str::<Schedule>(&json).unwrap(), schedule);
+    }
+    #[test]
+    fn export_partial_df2() {
+        let config = Config::new(3, 4, 2).unwrap();
+        let mut df2: DF2 = DF2::new(config.clone()).unwrap();
+        df2.step().unwrap();
+        let schedule = df2.schedule();
+        assert!(schedule
+            .rounds()
+            .iter()
+            .flatten()
+            .flatten()
+            .all(|&player| (player as usize) < config.player_count()));
+        assert!(schedule.player_count() <= config.player_count());
+        assert!(schedule.itineraries(None).len() <= config.player_count());
+        let csv = CsvExporter::default().export_string(&schedule).unwrap();
+        for row in csv.lines().skip(1) {
+            let player: usize = row.rsplit(',').next().unwrap().parse().unwrap();
+            assert!(player < config.player_count());
+        }
+    }
+    #[test]
+    fn import_formats() {
+        let config = Config::new(3, 4, 3).unwrap();
+        let state: State = State::new(config.clone()).unwrap();
+        let schedule = state.solutions().next().unwrap();
+        let complete = State::<u32>::from_schedule(config.clone(), &schedule).unwrap();
+        assert_eq!(complete.schedule(), schedule);
+        assert_eq!(
+            GridImporter.import(&complete.to_string()).unwrap(),
+            schedule
+        );
+        let csv = CsvExporter::default().export_string(&schedule).unwrap();
+        assert_eq!(CsvImporter.import(&csv).unwrap(), schedule);
+        let json = JsonExporter {
+            pretty: true,
+            roster: None,
+        }
+        .export_string(&schedule)
+        .unwrap();
+        assert_eq!(JsonImporter.import(&json).unwrap(), schedule);
+
+        // Rows can come in any order, and partial schedules are kept partial
+        let partial = CsvImporter
+            .import("round,table,seat,player\n1,1,1,3\n0,0,0,1\n1,1,0,0\n")
+            .unwrap();
+        assert_eq!(
+            partial.rounds(),
+            &vec![vec![vec![1], vec![]], vec![vec![], vec![0, 3]]]
+        );
+
+        // State accepts any schedule that keeps to the config, and can carry on from part of one
+        let relabelled: ScheduleGrid = schedule.rounds()[..2]
+            .iter()
+            .map(|tables| {
+                tables
+                    .iter()
+                    .map(|players| players.iter().map(|player| 11 - player).collect())
+                    .collect()
+            })
+            .collect();
+        let state: State =
+            State::from_schedule(config.clone(), &relabelled.clone().into()).unwrap();
+        let finished = state.solutions().next().unwrap();
+        assert!(finished.is_valid(&config));
+        for (finished, started) in finished.rounds().iter().zip(relabelled.iter()) {
+            for (finished, started) in finished.iter().zip(started.iter()) {
+                let mut started = started.clone();
+                started.sort_unstable();
+                assert_eq!(finished, &started);
+            }
+        }
+        let repeat = Schedule::new(vec![
+            vec![vec![0, 1, 2], vec![3, 4, 5]],
+            vec![vec![3, 6, 9], vec![0, 7, 2]],
+        ]);
+        assert!(matches!(
+            State::<u32>::from_schedule(config.clone(), &repeat),
+            Err(InitialisationError::Unplaceable {
+                round: 1,
+                table: 1,
+                player: 2
+            })
+        ));
+
+        // DF2 only accepts schedules its search could reach
+        let df2: DF2 = DF2::new(config.clone()).unwrap();
+        let schedule = df2.solutions().next().unwrap();
+        let mut resumed: DF2 = DF2::from_schedule(config.clone(), &schedule).unwrap();
+        assert!(matches!(
+            resumed.step(),
+            Err(StepError::FinishedStepping(_))
+        ));
+        assert_eq!(resumed.schedule(), schedule);
+        let first_rounds = Schedule::new(schedule.rounds()[..2].to_vec());
+        let resumed: DF2 = DF2::from_schedule(config.clone(), &first_rounds).unwrap();
+        assert!(resumed.solutions().next().unwrap().is_valid(&config));
+        assert!(matches!(
+            DF2::<u32>::from_schedule(config.clone(), &relabelled.into()),
+            Err(InitialisationError::Unplaceable { round: 0, .. })
+        ));
+
+        let errors = [
+            (
+                GridImporter.import("round 1"),
+                "Line 1: expected the header \"|  1  |  2  ...\"",
+            ),
+            (
+                GridImporter.import("     |  1  |  2\n-----+-----+-----\n     |  0  |  x\n"),
+                "Line 3, cell 3: \"x\" is not a valid number",
+            ),
+            (
+                GridImporter.import("     |  1  |  2\n     |  0  |  1\n"),
+                "Line 2: players are listed before the first round separator",
+            ),
+            (
+                CsvImporter.import("round,table,seat,player\n0,0,0,1\n0,0,0,2\n"),
+                "Line 3: seat 0 at table 0 in round 0 is already filled",
+            ),
+            (
+                CsvImporter.import("round,table,seat,player\n0,0,0,1\n0,0,2,2\n"),
+                "Line 3: seat 2 at table 0 in round 0 is filled but seat 1 is not",
+            ),
+            (
+                CsvImporter.import("round,table,seat,player\n0,0,0\n"),
+                "Line 2 has 3 cells but 4 were expected",
+            ),
+            (
+                CsvImporter.import("round,table,seat,player\n0,0,0,256\n"),
+                "Line 2, cell 4: \"256\" is not a valid number",
+            ),
+            (
+                CsvImporter.import("round,table,seat,player\n0,0,99999999999999,0\n"),
+                "Line 2, cell 3: 99999999999999 is more than the largest index allowed, 254",
+            ),
+            (
+                CsvImporter.import("round,table,seat,player\n255,0,0,0\n"),
+                "Line 2, cell 1: 255 is more than the largest index allowed, 254",
+            ),
+        ];
+        for (result, message) in errors.iter() {
+            assert_eq!(result.as_ref().unwrap_err().to_string(), *message);
+        }
+
+        // `DF2` grids import as the players placed, partway through and once finished
+        let config = Config::new(3, 4, 2).unwrap();
+        let empty_seat = config.player_count() as u8;
+        let mut df2: DF2 = DF2::new(config).unwrap();
+        for steps in 0.. {
+            let mut placed = df2.get_schedule();
+            for players in placed.iter_mut().flatten() {
+                players.retain(|&player| player != empty_seat);
+            }
+            let grid = df2.to_string();
+            assert_eq!(GridImporter.import(&grid).unwrap(), Schedule::new(placed));
+            if df2.step().is_err() {
+                assert!(steps > 0 && grid.contains('[') && !grid.contains('.'));
+                break;
+            }
+        }
+        assert!(JsonImporter
+            .import("{\"rounds\": [[[0, 1]], [[2, \"x\"]]]}")
+            .unwrap_err()
+            .to_string()
+            .contains("line 1"));
+    }
+    #[test]
+    fn roster() {
+        let roster = Roster::from_csv(
+            "id,name,club\n\
+             2,\"Smith, Jo\",Rooks\n\
+             0,Ann,Pawns\n\
+             1, \"Bo \"\"B\"\"\" ,\n\
+             3,Cy|d,Rooks\n",
+        )
+        .unwrap();
+        assert_eq!(roster.len(), 4);
+        assert_eq!(roster.label(2), "Smith, Jo");
+        assert_eq!(roster.label(1), "Bo \"B\"");
+        assert_eq!(roster.label(7), "7");
+        assert_eq!(roster.get(0).unwrap().metadata["club"], "Pawns");
+        assert_eq!(roster.id("Cy|d").unwrap(), 3);
+        assert!(roster.id("Dee").is_err());
+        assert_eq!(Roster::from_csv("name\nAnn\nBo\n").unwrap().label(1), "Bo");
+
+        let errors = [
+            (
+                Roster::from_csv("id,club\n"),
+                "Line 1: the header has no \"name\" column",
+            ),
+            (
+                Roster::from_csv("id,name\n0,Ann\n0,Bo\n"),
+                "Line 3: player id 0 is already used",
+            ),
+            (
+                Roster::from_csv("id,name\n0,Ann\n2,Bo\n"),
+                "No player has id 1, but ids must run from 0 without gaps",
+            ),
+            (
+                Roster::from_csv("name\nAnn\nAnn\n"),
+                "The name \"Ann\" is used more than once",
+            ),
+            (
+                Roster::from_csv("id,name\nx,Ann\n"),
+                "Line 2, cell 1: \"x\" is not a valid player id",
+            ),
+            (
+                Roster::from_csv("name,id\nAnn,99999999999999\n"),
+                "Line 2, cell 2: player id 99999999999999 is more than the largest allowed, 254",
+            ),
+            (
+                Roster::from_csv("name\n\"Ann\n"),
+                "Line 2: a quoted cell is never closed",
+            ),
+            (
+                Roster::from_csv("\n\nid,club\n0,Rooks\n"),
+                "Line 3: the header has no \"name\" column",
+            ),
+            (
+                Roster::new(vec!["Ann", "Bo\nB"]),
+                "The name \"Bo\\nB\" spans more than one line",
+            ),
+        ];
+        for (result, message) in errors.iter() {
+            assert_eq!(result.as_ref().unwrap_err().to_string(), *message);
+        }
+
+        // Names can stand in for player numbers when building a config
+        let names = (0..12).map(|player| format!("Player {}", player));
+        let roster = Roster::new(names).unwrap();
+        let config = Config::new(3, 4, 3)
+            .unwrap()
+            .with_roster(&roster)
+            .unwrap()
+            .with_forbidden_pair(
+                roster.id("Player 0").unwrap(),
+                roster.id("Player 5").unwrap(),
+            )
+            .unwrap();
+        assert_eq!(config.player_count(), 12);
+        assert!(Config::new(3, 4, 3)
+            .unwrap()
+            .with_roster(&Roster::new(vec!["Ann"]).unwrap())
+            .is_err());
+
+        // Every name a roster accepts survives a round trip through an exported CSV
+        let roster = Roster::new(vec![" Ann", "Bo \"B\", Jr", "Cy|d"]).unwrap();
+        let schedule = Schedule::new(vec![vec![vec![0, 1, 2]]]);
+        let csv = CsvExporter {
+            roster: Some(roster.clone()),
+        }
+        .export_string(&schedule)
+        .unwrap();
+        assert_eq!(CsvImporter.import(&csv).unwrap(), schedule);
+        let names: Vec<String> = csv
+            .lines()
+            .skip(1)
+            .map(|line| import::split_csv_line(line).unwrap()[4].clone())
+            .collect();
+        let labels: Vec<String> = (0..3).map(|player| roster.label(player)).collect();
+        assert_eq!(names, labels);
+        let json = serde_json::to_string(&roster).unwrap();
+        assert_eq!(serde_json::from_str::<Roster>(&json).unwrap(), roster);
+        let multiline = json.replace("Cy|d", "Cy\\nd");
+        assert!(serde_json::from_str::<Roster>(&multiline).is_err());
+
+        let roster = Roster::new(vec!["Ann", "Bo, Jr", "Cy|d", "Dee"]).unwrap();
+        let schedule = Schedule::new(vec![vec![vec![0, 1], vec![2, 3]]]);
+        let roster = Some(roster);
+        let csv = CsvExporter {
+            roster: roster.clone(),
+        }
+        .export_string(&schedule)
+        .unwrap();
+        assert_eq!(
+            csv,
+            "round,table,seat,player,name\n0,0,0,0,Ann\n0,0,1,1,\"Bo, Jr\"\n\
+             0,1,0,2,Cy|d\n0,1,1,3,Dee\n"
+        );
+        assert_eq!(CsvImporter.import(&csv).unwrap(), schedule);
+        assert_eq!(
+            MarkdownExporter {
+                roster: roster.clone()
+            }
+            .export_string(&schedule)
+            .unwrap(),
+            "| Round | Table 1 | Table 2 |\n| ---: | --- | --- |\n\
+             | 1 | Ann, Bo, Jr | Cy\\|d, Dee |\n"
+        );
+        assert_eq!(
+            GridExporter {
+                roster: roster.clone()
+            }
+            .export_string(&schedule)
+            .unwrap(),
+            "     |     1  |     2  \n\
+             -----+--------+--------\n\
+             \x20    |   Ann  |  Cy|d  \n\
+             \x20 1  |Bo, Jr  |   Dee  \n\
+             \x20    |        |        "
+        );
+        let json = JsonExporter {
+            pretty: false,
+            roster,
+        }
+        .export_string(&schedule)
+        .unwrap();
+        assert_eq!(
+            json,
+            "{\"rounds\":[[[0,1],[2,3]]],\"players\":[{\"name\":\"Ann\"},\
+             {\"name\":\"Bo, Jr\"},{\"name\":\"Cy|d\"},{\"name\":\"Dee\"}]}\n"
+        );
+        assert_eq!(JsonImporter.import(&json).unwrap(), schedule);
+    }
+    #[test]
+    fn itineraries() {
+        let schedule = Schedule::new(vec![
+            vec![vec![0, 1], vec![2, 3]],
+            vec![vec![4, 0], vec![1, 2]],
+        ]);
+        assert_eq!(schedule.player_count(), 5);
+        assert_eq!(
+            schedule.itinerary(3),
+            Itinerary {
+                player: 3,
+                name: None,
+                stops: vec![
+                    Stop {
+                        round: 0,
+                        table: Some(1),
+                        tablemates: vec![2]
+                    },
+                    Stop {
+                        round: 1,
+                        table: None,
+                        tablemates: vec![]
+                    },
+                ],
+            }
+        );
+        let roster = Roster::new(vec!["Ann", "Bo", "Cy", "Dee", "Ed, Jr", "Flo"]).unwrap();
+        let itineraries = schedule.itineraries(Some(&roster));
+        assert_eq!(itineraries.len(), 6);
+        assert_eq!(itineraries[5].name.as_deref(), Some("Flo"));
+        assert!(itineraries[5].stops.iter().all(|stop| stop.table.is_none()));
+        // Players from a roster too long to seat are not mistaken for those they wrap around to
+        assert!(schedule.itinerary(256).stops.iter().all(|stop| stop.table.is_none()));
+
+        let text = TextItineraryExporter {
+            roster: Some(roster.clone()),
+        }
+        .export_string(&schedule)
+        .unwrap();
+        assert!(text.starts_with(
+            "Ann\n  Round 1: table 1 with Bo\n  Round 2: table 1 with Ed, Jr\n\n\
+             Bo\n  Round 1: table 1 with Ann\n  Round 2: table 2 with Cy\n"
+        ));
+        assert!(text.ends_with("Flo\n  Round 1: sitting out\n  Round 2: sitting out\n"));
+        let csv = CsvItineraryExporter::default()
+            .export_string(&schedule)
+            .unwrap();
+        assert!(csv.starts_with("player,round,table,tablemates\n0,0,0,1\n0,1,0,4\n"));
+        assert!(csv.contains("\n3,1,,\n"));
+        let csv = CsvItineraryExporter {
+            roster: Some(roster.clone()),
+        }
+        .export_string(&schedule)
+        .unwrap();
+        assert!(csv.contains("\n4,\"Ed, Jr\",1,0,Ann\n"));
+        let json = JsonItineraryExporter {
+            pretty: false,
+            roster: None,
+        }
+        .export_string(&schedule)
+        .unwrap();
+        assert!(json.starts_with(
+            "[{\"player\":0,\"stops\":[{\"round\":0,\"table\":0,\"tablemates\":[1]},"
+        ));
+        let parsed: Vec<Itinerary> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, schedule.itineraries(None));
+
+        // Both solvers' schedules give every player a table in every round
+        let config = Config::new(3, 4, 3).unwrap();
+        let state: State = State::new(config.clone()).unwrap();
+        let df2: DF2 = DF2::new(config).unwrap();
+        for schedule in [state.solutions().next(), df2.solutions().next()].iter() {
+            let schedule = schedule.as_ref().unwrap();
+            for itinerary in schedule.itineraries(None) {
+                for stop in itinerary.stops.iter() {
+                    assert!(stop.table.is_some());
+                    assert_eq!(stop.tablemates.len(), 2);
+                }
+            }
+        }
+    }
+    #[test]
+    fn meeting_report() {
+        let schedule = Schedule::new(vec![
+            vec![vec![0, 1, 2], vec![3, 4]],
+            vec![vec![0, 3], vec![1, 2, 4]],
+        ]);
+        let report = schedule.meeting_report(6);
+        assert_eq!(report.player_count(), 6);
+        assert_eq!(report.counts[1][2], 2);
+        assert_eq!(report.counts[2][1], 2);
+        assert_eq!(report.counts[0][4], 0);
+        assert_eq!(
+            report.never_met,
+            vec![
+                (0, 4),
+                (0, 5),
+                (1, 3),
+                (1, 5),
+                (2, 3),
+                (2, 5),
+                (3, 5),
+                (4, 5)
+            ]
+        );
+        assert_eq!(report.met_repeatedly, vec![(1, 2, 2)]);
+        assert_eq!(report.distinct_opponents, vec![3, 3, 3, 2, 3, 0]);
+        assert_eq!(
+            report.to_string(),
+            "     0  1  2  3  4  5\n \
+             0:  -  1  1  1  .  .\n \
+             1:  1  -  2  .  1  .\n \
+             2:  1  2  -  .  1  .\n \
+             3:  1  .  .  -  1  .\n \
+             4:  .  1  1  1  -  .\n \
+             5:  .  .  .  .  .  -\n\
+             \n\
+             Pairs met: 46.7%\n\
+             Never met: 0 & 4, 0 & 5, 1 & 3, 1 & 5, 2 & 3, 2 & 5, 3 & 5, 4 & 5\n\
+             Met more than once: 1 & 2 (2 times)\n\
+             Distinct opponents:\n  0: 3\n  1: 3\n  2: 3\n  3: 2\n  4: 3\n  5: 0\n"
+        );
+        let roster = Roster::new(vec!["Ann", "Bo", "Cy", "Dee", "Ed", "Flo"]).unwrap();
+        let mut named = String::new();
+        report.format(Some(&roster), &mut named).unwrap();
+        assert!(named.contains("Met more than once: Bo & Cy (2 times)\n"));
+
+        // Players listed twice at a table, as schedules read from a file may do, meet once
+        let repeated = Schedule::new(vec![vec![vec![0, 1, 1]], vec![vec![2, 2, 0]]]);
+        let report = repeated.meeting_report(3);
+        assert_eq!(report.counts[0][1], 1);
+        assert_eq!(report.counts[2][2], 0);
+        assert!(report.met_repeatedly.is_empty());
+
+        // A valid schedule never repeats a meeting
+        let config = Config::new(3, 4, 3).unwrap();
+        let df2: DF2 = DF2::new(config.clone()).unwrap();
+        let report = df2
+            .solutions()
+            .next()
+            .unwrap()
+            .meeting_report(config.player_count());
+        assert!(report.met_repeatedly.is_empty());
+        assert!(report.distinct_opponents.iter().all(|&count| count == 6));
+        assert_eq!(report.never_met.len(), 12 * 11 / 2 - 12 * 6 / 2);
+    }
+    #[test]
+    fn df2_display() {
+        let mut df2: DF2 = DF2::new(Config::new(2, 3, 2).unwrap()).unwrap();
+        df2.step().unwrap();
+        df2.step().unwrap();
+        let expected = [
+            "     |  1  |  2  |  3  ",
+            "-----+-----+-----+-----",
+            "     |  0  |  2  |  4  ",
+            "  1  |  1  |  3  |  5  ",
+            "     |     |     |     ",
+            "-----+-----+-----+-----",
+            "     |  2  |  .  |  .  ",
+            "  2  |[4]  |  .  |  .  ",
+            "     |     |     |     ",
+        ];
+        assert_eq!(df2.to_string(), expected.join("\n"));
+    }
+
+    #[test]
+    fn checkpoint_resume() {
+        let config = Config::new(3, 4, 2).unwrap();
+        let df2: DF2 = DF2::new(config.clone()).unwrap();
+        let all: Vec<Schedule> = df2.clone().solutions().collect();
+
+        // Solution iterators carry on from where they were saved
+        let mut solutions = df2.clone().solutions();
+        let mut resumed: Vec<Schedule> = solutions.by_ref().take(5).collect();
+        let mut checkpoint = Vec::new();
+        solutions.write_checkpoint(&mut checkpoint).unwrap();
+        let solutions = DF2Solutions::<u32>::read_checkpoint(&mut &checkpoint[..]).unwrap();
+        resumed.extend(solutions);
+        assert_eq!(resumed, all);
+
+        let state: State = State::new(config.clone()).unwrap();
+        let all: Vec<Schedule> = state.clone().solutions().collect();
+        let mut solutions = state.solutions();
+        let mut resumed = Vec::new();
+        for _ in 0..100 {
+            if let SearchStep::Found(schedule) = solutions.step() {
+                resumed.push(schedule);
+            }
+        }
+        assert!(!resumed.is_empty() && resumed.len() < all.len());
+        let path = std::env::temp_dir().join(format!("checkpoint-{}", std::process::id()));
+        solutions.save_checkpoint(&path).unwrap();
+        let solutions = StateSolutions::<u32>::load_checkpoint(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        resumed.extend(solutions);
+        assert_eq!(resumed, all);
+
+        // A search saved part way through steps exactly as it would have
+        let mut df2 = df2;
+        for _ in 0..20 {
+            df2.step().unwrap();
+        }
+        let mut checkpoint = Vec::new();
+        df2.write_checkpoint(&mut checkpoint).unwrap();
+        let mut resumed = DF2::<u32>::read_checkpoint(&mut &checkpoint[..]).unwrap();
+        assert_eq!(resumed.to_string(), df2.to_string());
+        for _ in 0..20 {
+            assert_eq!(resumed.step().is_ok(), df2.step().is_ok());
+            assert_eq!(resumed.get_schedule(), df2.get_schedule());
+        }
+
+        // Checkpoints are only resumed as the search that wrote them
+        assert!(matches!(
+            DF2::<u64>::read_checkpoint(&mut &checkpoint[..]),
+            Err(CheckpointError::WrongPlayerSet {
+                found: 32,
+                expected: 64
+            })
+        ));
+        assert!(matches!(
+            DF2Solutions::<u32>::read_checkpoint(&mut &checkpoint[..]),
+            Err(CheckpointError::WrongSearch { .. })
+        ));
+        let mut future = checkpoint.clone();
+        future[8] += 1;
+        assert!(matches!(
+            DF2::<u32>::read_checkpoint(&mut &future[..]),
+            Err(CheckpointError::UnsupportedVersion {
+                found: 2,
+                supported: 1
+            })
+        ));
+        assert!(matches!(
+            DF2::<u32>::read_checkpoint(&mut &b"round,table"[..]),
+            Err(CheckpointError::NotACheckpoint)
+        ));
+        assert!(matches!(
+            DF2::<u32>::read_checkpoint(&mut &checkpoint[..checkpoint.len() - 1]),
+            Err(CheckpointError::Encoding(_))
+        ));
+
+        // Configs are checked as they are read, rather than trusted by the search
+        let constrained = Config::new(3, 4, 2)
+            .unwrap()
+            .with_player_count(9)
+            .unwrap()
+            .with_available_rounds(8, &[0, 1])
+            .unwrap()
+            .with_pin(2, 1, 3)
+            .unwrap()
+            .with_forbidden_pair(0, 5)
+            .unwrap()
+            .with_must_meet_pair(6, 1)
+            .unwrap();
+        let encoded = bincode::serialize(&constrained).unwrap();
+        let decoded: Config = bincode::deserialize(&encoded).unwrap();
+        assert_eq!(decoded, constrained);
+        type RawConfig = (
+            Vec<Vec<usize>>,
+            usize,
+            Vec<Vec<usize>>,
+            Vec<Vec<Vec<usize>>>,
+            Vec<(usize, usize)>,
+            Vec<(usize, usize)>,
+        );
+        let valid = bincode::serialize(&config).unwrap();
+        let start = checkpoint
+            .windows(valid.len())
+            .position(|window| window == &valid[..])
+            .unwrap();
+        let splice = |raw: &RawConfig| {
+            let mut spliced = checkpoint[..start].to_vec();
+            spliced.extend(bincode::serialize(raw).unwrap());
+            spliced.extend(&checkpoint[start + valid.len()..]);
+            DF2::<u32>::read_checkpoint(&mut &spliced[..])
         };
-        for player in 0..PLAYER_COUNT as u8 {
-            new.apply_player(player);
-            new.increment().unwrap();
+        let (seats, absent, pins) = (
+            vec![vec![2; 4]; 3],
+            vec![vec![]; 3],
+            vec![vec![vec![]; 4]; 3],
+        );
+        let raw: RawConfig = (seats, 8, absent, pins, vec![], vec![]);
+        assert!(splice(&raw).is_ok());
+        let mut corrupted = vec![raw; 5];
+        corrupted[0].0.clear();
+        // Jagged tables
+        corrupted[1].0[1].pop();
+        corrupted[2].2[0].push(9);
+        corrupted[3].3[0][0] = vec![0, 0];
+        corrupted[4].4.push((3, 3));
+        for raw in corrupted.iter() {
+            assert!(matches!(splice(raw), Err(CheckpointError::Encoding(_))));
+        }
+
+        // Fields worked out from the config must agree with it
+        let tampered: Vec<fn(&mut DF2)> = vec![
+            |df2| df2.symmetric = !df2.symmetric,
+            |df2| df2.round_zero_fixed = !df2.round_zero_fixed,
+            |df2| df2.available[1].remove(3),
+            |df2| df2.forbidden[2].insert(4),
+            |df2| df2.fixed_placed = 0,
+            // Sets that disagree with the schedule, or hold players not in the config
+            |df2| df2.played_in_round[0] ^= 1 << 5,
+            |df2| df2.played_on_table_total[3] ^= 1,
+            |df2| df2.players_played_with[1] ^= 1 << 7,
+            |df2| df2.players_played_with[8] ^= 1,
+            |df2| df2.removed[2][1] ^= 1 << 8,
+            |df2| df2.forced[1].0 ^= 1 << 9,
+        ];
+        for tamper in tampered {
+            let mut df2: DF2 = DF2::new(config.clone()).unwrap();
+            df2.step().unwrap();
+            tamper(&mut df2);
+            let mut checkpoint = Vec::new();
+            df2.write_checkpoint(&mut checkpoint).unwrap();
+            assert!(matches!(
+                DF2::<u32>::read_checkpoint(&mut &checkpoint[..]),
+                Err(CheckpointError::Inconsistent)
+            ));
+        }
+        let tampered: Vec<fn(&mut Progress<u32>)> = vec![
+            |progress| progress.forbidden[2].insert(4),
+            |progress| progress.played_in_round[1] ^= 1 << 2,
+            |progress| progress.played_on_table[0][0] ^= 1 << 6,
+            |progress| progress.played_on_table_total[2] ^= 1,
+            |progress| progress.players_played_with[0] ^= 1 << 3,
+            |progress| progress.potential_on_table[2][3] ^= 1 << 8,
+        ];
+        for tamper in tampered {
+            let mut state: State = State::new(config.clone()).unwrap();
+            tamper(&mut state.progress);
+            let mut checkpoint = Vec::new();
+            state.write_checkpoint(&mut checkpoint).unwrap();
+            assert!(matches!(
+                State::<u32>::read_checkpoint(&mut &checkpoint[..]),
+                Err(CheckpointError::Inconsistent)
+            ));
+        }
+    }
+
+    #[test]
+    fn frontier_search() {
+        let directory = std::env::temp_dir().join(format!("frontier-test-{}", std::process::id()));
+        let config = Config::new(3, 4, 2).unwrap();
+        let state: State = State::new(config.clone()).unwrap();
+        let all: std::collections::HashSet<Schedule> = state.clone().solutions().collect();
+
+        // With no memory to spare every state outside the explored bucket goes to disk
+        let mut search = FrontierSearch::new(state.clone(), &directory)
+            .unwrap()
+            .with_memory_budget(0);
+        let search_directory = search.directory().to_path_buf();
+        let mut found = std::collections::HashSet::new();
+        let mut most_spilled = 0;
+        loop {
+            match search.step().unwrap() {
+                SearchStep::Searching => {}
+                SearchStep::Found(schedule) => assert!(found.insert(schedule)),
+                SearchStep::Finished => break,
+            }
+            most_spilled = most_spilled.max(search.spilled());
+        }
+        assert!(most_spilled > 0);
+        assert_eq!(found, all);
+        drop(search);
+        assert!(!search_directory.exists());
+
+        // Stopping early still cleans up
+        let mut search = FrontierSearch::new(state, &directory)
+            .unwrap()
+            .with_memory_budget(1);
+        let search_directory = search.directory().to_path_buf();
+        assert!(search.next().unwrap().is_ok());
+        drop(search);
+        assert!(!search_directory.exists());
+        std::fs::remove_dir(&directory).unwrap();
+
+        // Tables of three, and players sitting out with a pair who must meet
+        for config in [
+            Config::new(3, 4, 3).unwrap().with_player_count(12).unwrap(),
+            Config::new(2, 3, 2)
+                .unwrap()
+                .with_player_count(7)
+                .unwrap()
+                .with_must_meet_pair(1, 4)
+                .unwrap(),
+        ] {
+            let state: State = State::new(config).unwrap();
+            let all: std::collections::HashSet<Schedule> = state.clone().solutions().collect();
+            let found: std::collections::HashSet<Schedule> =
+                FrontierSearch::new(state, &std::env::temp_dir())
+                    .unwrap()
+                    .with_memory_budget(10)
+                    .map(Result::unwrap)
+                    .collect();
+            assert!(!all.is_empty());
+            assert_eq!(found, all);
+        }
+    }
+
+    #[test]
+    fn parallel_df2() {
+        for config in [
+            Config::new(3, 4, 2).unwrap(),
+            Config::new(3, 4, 3).unwrap().with_player_count(12).unwrap(),
+            Config::new(2, 3, 2)
+                .unwrap()
+                .with_player_count(7)
+                .unwrap()
+                .with_must_meet_pair(1, 4)
+                .unwrap(),
+            Config::new(3, 3, 2).unwrap().with_pin(1, 0, 2).unwrap(),
+        ] {
+            let df2: DF2 = DF2::new(config.clone()).unwrap();
+            let all: Vec<Schedule> = df2.solutions().collect();
+            let parallel: ParallelDF2 = ParallelDF2::new(config.clone()).unwrap().with_threads(3);
+
+            // Searching each prefix on its own finds every schedule once, in the same order
+            let prefixes = parallel.prefixes();
+            assert!(prefixes.len() > 1);
+            let subtrees: Vec<Schedule> = prefixes
+                .iter()
+                .flat_map(|prefix| {
+                    DF2::<u32>::from_slice(config.clone(), prefix)
+                        .unwrap()
+                        .subtree()
+                        .solutions()
+                })
+                .collect();
+            assert_eq!(subtrees, all);
+
+            let count = parallel.count(None);
+            assert_eq!(count.solutions, all.len() as u64);
+            match parallel.find() {
+                SearchOutcome::Found(schedule) => {
+                    assert!(schedule.validate(&config).is_empty());
+                    assert!(all.contains(&schedule));
+                }
+                outcome => panic!("{:?}", outcome),
+            }
+        }
+
+        let config = Config::new(3, 4, 2).unwrap();
+        let filter = IsomorphismFilter::new(&config).unwrap();
+        let parallel: ParallelDF2 = ParallelDF2::new(config).unwrap().with_threads(4);
+        assert_eq!(parallel.count(Some(filter)).unique, Some(3));
+
+        let parallel: ParallelDF2 = ParallelDF2::new(Config::new(4, 4, 4).unwrap()).unwrap();
+        assert_eq!(parallel.with_threads(2).find(), SearchOutcome::Infeasible);
+    }
+
+    #[test]
+    fn search_limits() {
+        let hard = Config::new(6, 6, 4).unwrap();
+        let limits = SearchLimits::new().with_nodes(1000);
+        let df2: DF2 = DF2::new(hard.clone()).unwrap();
+        let mut solutions = df2.solutions();
+        match solutions.find(&limits) {
+            SearchOutcome::LimitReached {
+                limit: Limit::Nodes,
+                best_partial: Some(partial),
+            } => {
+                let placed: usize = partial.rounds().iter().flatten().map(Vec::len).sum();
+                assert_eq!(placed, solutions.stats().best_players_placed);
+                assert!(placed < hard.seat_count() * hard.round_count());
+            }
+            outcome => panic!("{:?}", outcome),
+        }
+        assert_eq!(solutions.stats().nodes, 1000);
+
+        let state: State = State::new(hard.clone()).unwrap();
+        let mut solutions = state.solutions();
+        let limits = SearchLimits::new().with_backtracks(10);
+        assert!(matches!(
+            solutions.find(&limits),
+            SearchOutcome::LimitReached {
+                limit: Limit::Backtracks,
+                ..
+            }
+        ));
+        assert_eq!(solutions.stats().backtracks, 10);
+        let limits = SearchLimits::new().with_time(std::time::Duration::ZERO);
+        assert!(matches!(
+            solutions.find(&limits),
+            SearchOutcome::LimitReached {
+                limit: Limit::Time,
+                ..
+            }
+        ));
+
+        // Cancelling stops searches on every thread
+        let token = CancellationToken::new();
+        let limits = SearchLimits::new().with_cancellation(token.clone());
+        let parallel: ParallelState = ParallelState::new(hard.clone())
+            .unwrap()
+            .with_threads(2)
+            .with_limits(limits.clone());
+        let parallel_df2: ParallelDF2 = ParallelDF2::new(hard)
+            .unwrap()
+            .with_threads(2)
+            .with_limits(SearchLimits::new().with_nodes(100_000));
+        std::thread::scope(|scope| {
+            let search = scope.spawn(|| parallel.find());
+            // Wait for a partial schedule to be shared, as the search never finishes
+            while parallel.stats().snapshot().nodes == 0 {
+                std::thread::yield_now();
+            }
+            token.cancel();
+            assert!(matches!(
+                search.join().unwrap(),
+                SearchOutcome::LimitReached {
+                    limit: Limit::Cancelled,
+                    best_partial: Some(_),
+                }
+            ));
+        });
+        assert!(matches!(
+            parallel_df2.find(),
+            SearchOutcome::LimitReached {
+                limit: Limit::Nodes,
+                ..
+            }
+        ));
+        assert!(parallel_df2.stats().snapshot().nodes >= 100_000);
+
+        // Limits that are not reached leave the search to finish
+        let config = Config::new(3, 4, 2).unwrap();
+        let state: State = State::new(config.clone()).unwrap();
+        let mut frontier = FrontierSearch::new(state, &std::env::temp_dir()).unwrap();
+        assert!(matches!(
+            frontier.find(&SearchLimits::new().with_nodes(3)).unwrap(),
+            SearchOutcome::LimitReached {
+                limit: Limit::Nodes,
+                ..
+            }
+        ));
+        match frontier.find(&SearchLimits::new()).unwrap() {
+            SearchOutcome::Found(schedule) => assert!(schedule.validate(&config).is_empty()),
+            outcome => panic!("{:?}", outcome),
+        }
+        let df2: DF2 = DF2::new(Config::new(4, 4, 4).unwrap()).unwrap();
+        let limits = SearchLimits::new().with_time(std::time::Duration::from_secs(60));
+        assert_eq!(df2.solutions().find(&limits), SearchOutcome::Infeasible);
+
+        // Running out after finding schedules is not reported as there being none
+        let mut solutions = DF2::<u32>::new(Config::new(3, 4, 2).unwrap())
+            .unwrap()
+            .solutions();
+        let mut outcome = solutions.find(&limits);
+        while let SearchOutcome::Found(_) = outcome {
+            outcome = solutions.find(&limits);
         }
-        new.decrement().unwrap();
-        new.players_placed = 24;
+        assert_eq!(outcome, SearchOutcome::Exhausted);
+    }
+
+    #[test]
+    fn search_stats() {
+        let config = Config::new(3, 4, 2).unwrap();
+        let df2: DF2 = DF2::new(config.clone()).unwrap();
+        let mut solutions = df2.solutions();
+        assert_eq!(solutions.stats().nodes, 0);
+        let count = solutions.by_ref().count() as u64;
+        let stats = solutions.stats();
+        assert_eq!(stats.solutions, count);
+        assert_eq!(stats.depth_histogram.iter().sum::<u64>(), stats.nodes);
+        assert!(stats.backtracks > 0 && stats.backtracks < stats.nodes);
+        assert_eq!(stats.best_players_placed, 24);
+        assert_eq!(stats.depth_range(), Some((8, 24)));
+        let best = stats.best_partial.as_ref().unwrap();
+        assert!(best.validate(&config).is_empty());
 
-        new
+        // Subtrees searched on their own add up to the whole search
+        let parallel: ParallelDF2 = ParallelDF2::new(config).unwrap().with_threads(2);
+        parallel.count(None);
+        let parallel_stats = parallel.stats().snapshot();
+        assert_eq!(parallel_stats.solutions, count);
+        assert_eq!(parallel_stats.best_players_placed, 24);
+        let mut merged = SearchStats::default();
+        merged.merge(&stats);
+        merged.merge(&stats);
+        assert_eq!(merged.nodes, stats.nodes * 2);
+        assert_eq!(merged.best_partial, stats.best_partial);
+        assert!(merged.to_string().starts_with("Steps: "));
     }
-    pub fn from_slice(players: &[u8]) -> Result<Self, InitialisationError> {
-        let mut df = Self::new();
-        for player in players.iter() {
-            df.increment()?;
-            if df.get_mask(df.round, df.table) & (1 << player) == 0 {
-                Err(PlayerNotPlacable{})?;
+
+    #[test]
+    fn parallel_state() {
+        for config in [
+            Config::new(3, 4, 2).unwrap(),
+            Config::new(3, 4, 3).unwrap().with_player_count(12).unwrap(),
+            Config::new(2, 3, 2)
+                .unwrap()
+                .with_player_count(7)
+                .unwrap()
+                .with_must_meet_pair(1, 4)
+                .unwrap(),
+        ] {
+            let state: State = State::new(config.clone()).unwrap();
+            let mut solutions = state.solutions();
+            let serial = count_solutions(solutions.by_ref(), None);
+            let parallel: ParallelState =
+                ParallelState::new(config.clone()).unwrap().with_threads(4);
+            assert_eq!(parallel.count(None).solutions, serial.solutions);
+
+            // The threads explore the same states between them
+            let stats = parallel.stats().snapshot();
+            let serial_stats = solutions.stats();
+            assert_eq!(stats.solutions, serial.solutions);
+            assert_eq!(stats.nodes, serial_stats.nodes);
+            assert_eq!(stats.backtracks, serial_stats.backtracks);
+            assert_eq!(stats.depth_histogram, serial_stats.depth_histogram);
+            let seats = config.seat_count() * config.round_count();
+            assert_eq!(stats.best_players_placed, seats);
+            match parallel.find() {
+                SearchOutcome::Found(schedule) => assert!(schedule.validate(&config).is_empty()),
+                outcome => panic!("{:?}", outcome),
             }
-            df.apply_player(*player);
         }
 
-        Ok(df)
-    }
-    fn toggle_player(&mut self, player: u8) {
-        assert!(player < PLAYER_COUNT as u8);
-        let round = self.round;
-        let table = self.table;
-        let player_mask: u32 = 1 << player;
-        self.played_in_round[round as usize] ^= player_mask;
-        self.played_on_table_total[table as usize] ^= player_mask;
-        let players_in_round = self.schedule[round as usize][table as usize];
-        self.players_played_with[players_in_round[0] as usize & 31] ^= player_mask;
-        self.players_played_with[players_in_round[1] as usize & 31] ^= player_mask;
-        self.players_played_with[players_in_round[2] as usize & 31] ^= player_mask;
-        self.players_played_with[players_in_round[3] as usize & 31] ^= player_mask;
-        self.players_played_with[PLAYER_COUNT] = 0;
+        let config = Config::new(3, 4, 2).unwrap();
+        let filter = IsomorphismFilter::new(&config).unwrap();
+        let parallel: ParallelState = ParallelState::new(config).unwrap().with_threads(3);
+        assert_eq!(parallel.count(Some(filter)).unique, Some(3));
+
+        // Four players can only meet in three rounds of pairs
+        let parallel: ParallelState = ParallelState::new(Config::new(4, 2, 2).unwrap()).unwrap();
+        assert_eq!(parallel.with_threads(2).find(), SearchOutcome::Infeasible);
     }
-    fn apply_player(&mut self, player: u8) {
-        assert!(player < PLAYER_COUNT as u8);
-        debug_assert_ne!(self.get_mask(self.round, self.table) & (1 << player), 0);
-        self.schedule[self.round as usize][self.table as usize][self.player_number as usize] =
-            player as u8;
-        log::trace!(
-            "placing player {} into {:?}",
-            player,
-            self.schedule[self.round as usize][self.table as usize]
-        );
-        self.toggle_player(player);
+
+    #[test]
+    fn search_observer() {
+        /// Rebuilds the partial schedule from placements and removals
+        #[derive(Default)]
+        struct Replay {
+            tables: std::collections::BTreeMap<(usize, usize), Vec<usize>>,
+            removed: u64,
+            backtracked: u64,
+            dead_ends: u64,
+            solutions: u64,
+        }
+        impl Replay {
+            fn new(start: Vec<Vec<Vec<u8>>>, empty_seat: u8) -> Self {
+                let mut replay = Self::default();
+                for (round, tables) in start.into_iter().enumerate() {
+                    for (table, players) in tables.into_iter().enumerate() {
+                        let players = players.into_iter().filter(|&p| p != empty_seat);
+                        let players = players.map(usize::from).collect();
+                        replay.tables.insert((round, table), players);
+                    }
+                }
+                replay
+            }
+        }
+        impl SearchObserver for Replay {
+            fn placed(&mut self, round: Round, table: Table, player: usize) {
+                let players = self.tables.entry((round.index(), table.index()));
+                players.or_default().push(player);
+            }
+            fn removed(&mut self, round: Round, table: Table, player: usize) {
+                let players = self
+                    .tables
+                    .get_mut(&(round.index(), table.index()))
+                    .unwrap();
+                let seat = players.iter().position(|&p| p == player).unwrap();
+                players.remove(seat);
+                self.removed += 1;
+            }
+            fn backtracked(&mut self, players_placed: usize) {
+                assert_eq!(
+                    self.tables.values().map(Vec::len).sum::<usize>(),
+                    players_placed
+                );
+                self.backtracked += 1;
+            }
+            fn dead_end(&mut self, _players_placed: usize) {
+                self.dead_ends += 1;
+            }
+            fn solution_found(&mut self, schedule: &Schedule) {
+                for (round, tables) in schedule.rounds().iter().enumerate() {
+                    for (table, players) in tables.iter().enumerate() {
+                        let mut placed = self.tables[&(round, table)].clone();
+                        placed.sort_unstable();
+                        let players: Vec<usize> = players.iter().map(|&p| p as usize).collect();
+                        assert_eq!(placed, players);
+                    }
+                }
+                self.solutions += 1;
+            }
+        }
+
+        let config = Config::new(3, 4, 2).unwrap();
+        let empty_seat = config.player_count() as u8;
+        let df2: DF2 = DF2::new(config.clone()).unwrap();
+        let mut replay = Replay::new(df2.get_schedule(), empty_seat);
+        let mut solutions = df2.solutions();
+        while solutions.step_observed(&mut replay) != SearchStep::Finished {}
+        let stats = solutions.stats();
+        assert_eq!(replay.solutions, stats.solutions);
+        assert_eq!(replay.removed, stats.backtracks);
+        assert!(replay.dead_ends > 0);
+        // Every solution and dead end is gone back from but the last
+        assert_eq!(replay.backtracked, replay.solutions + replay.dead_ends - 1);
+
+        let state: State = State::new(config.clone()).unwrap();
+        let mut replay = Replay::new(state.get_schedule(), empty_seat);
+        let mut solutions = state.solutions();
+        while solutions.step_observed(&mut replay) != SearchStep::Finished {}
+        let stats = solutions.stats();
+        assert_eq!(replay.solutions, 768);
+        assert_eq!(replay.dead_ends, stats.backtracks);
+        assert_eq!(replay.backtracked, replay.solutions + replay.dead_ends - 1);
+
+        /// Counts events across every clone, for the searches that give each thread its own
+        #[derive(Clone, Default)]
+        struct Counts(Arc<std::sync::Mutex<(u64, u64, u64)>>);
+        impl SearchObserver for Counts {
+            fn placed(&mut self, _round: Round, _table: Table, _player: usize) {
+                self.0.lock().unwrap().0 += 1;
+            }
+            fn dead_end(&mut self, _players_placed: usize) {
+                self.0.lock().unwrap().1 += 1;
+            }
+            fn solution_found(&mut self, _schedule: &Schedule) {
+                self.0.lock().unwrap().2 += 1;
+            }
+        }
+
+        let state: State = State::new(config.clone()).unwrap();
+        let mut frontier = FrontierSearch::new(state, &std::env::temp_dir()).unwrap();
+        let mut counts = Counts::default();
+        while frontier.step_observed(&mut counts).unwrap() != SearchStep::Finished {}
+        let (placed, dead_ends, solutions) = *counts.0.lock().unwrap();
+        assert!(placed > 0);
+        assert_eq!(dead_ends, frontier.stats().backtracks);
+        assert_eq!(solutions, 768);
+
+        let counts = Counts::default();
+        let parallel: ParallelState = ParallelState::new(config.clone()).unwrap();
+        let count = parallel.with_threads(3).count_observed(None, &counts);
+        let (placed, _, solutions) = *counts.0.lock().unwrap();
+        assert!(placed > 0);
+        assert_eq!(solutions, count.solutions);
+
+        let counts = Counts::default();
+        let parallel: ParallelDF2 = ParallelDF2::new(config).unwrap();
+        let count = parallel.with_threads(3).count_observed(None, &counts);
+        let (placed, dead_ends, solutions) = *counts.0.lock().unwrap();
+        assert!(placed > 0 && dead_ends > 0);
+        assert_eq!(solutions, count.solutions);
     }
-    fn last_player(&self) -> u8 {
-        self.schedule[self.round as usize][self.table as usize][self.player_number]
+
+    #[test]
+    fn config_limits() {
+        assert!(Config::new(0, 6, 4).is_err());
+        assert!(Config::new(6, 6, 0).is_err());
+        assert!(Config::new(6, 64, 4).is_err());
+        assert!(Config::new(256, 6, 4).is_err());
+        assert!(Config::new(7, 8, 3).is_ok());
+        assert!(Config::new(9, 15, 4).is_ok());
+        assert!(Config::default().round(6).is_err());
+        assert!(Config::default().table(5).is_ok());
     }
-    fn remove_last_player(&mut self) {
-        let player = self.last_player();
-        assert!(player < PLAYER_COUNT as u8);
-        log::trace!(
-            "removing player {} from {:?}",
-            player,
-            self.schedule[self.round as usize][self.table as usize]
+    #[test]
+    fn df2_other_dimensions() {
+        let mut state: DF2 = DF2::new(Config::new(4, 4, 3).unwrap()).unwrap();
+        loop {
+            match state.step() {
+                Ok(()) => {}
+                Err(StepError::FinishedStepping(_)) => break,
+                Err(err) => panic!("{}", err),
+            }
+        }
+        assert_eq!(
+            state.get_schedule(),
+            vec![
+                vec![vec![0, 1, 2], vec![3, 4, 5], vec![6, 7, 8], vec![9, 10, 11]],
+                vec![vec![3, 6, 9], vec![0, 7, 10], vec![1, 4, 11], vec![2, 5, 8]],
+                vec![vec![4, 8, 10], vec![2, 6, 11], vec![0, 5, 9], vec![1, 3, 7]],
+                vec![vec![5, 7, 11], vec![1, 8, 9], vec![2, 3, 10], vec![0, 4, 6]],
+            ]
         );
-        self.schedule[self.round as usize][self.table as usize][self.player_number] =
-            PLAYER_COUNT as u8;
-        self.toggle_player(player);
     }
-    const fn get_mask(&self, round: Round, table: Table) -> u32 {
-        /* TODO
-        - First person of each round must be greater than last, otherwise waste time on multiple identical solutions
-        */
-
-        let players_in_round = self.schedule[round as usize][table as usize];
+    #[test]
+    fn state_other_dimensions() {
+        let config = Config::new(4, 4, 3).unwrap();
+        let finished = run_state::<u32>(&config);
+        assert_eq!(finished.get_players_played_count(), 4 * 12);
 
-        PLAYER_MASK
-            & !self.played_in_round[round as usize]
-            & !self.played_on_table_total[table as usize]
-            & !self.players_played_with[players_in_round[0] as usize & 31]
-            & !self.players_played_with[players_in_round[1] as usize & 31]
-            & !self.players_played_with[players_in_round[2] as usize & 31]
-            & !self.players_played_with[players_in_round[3] as usize & 31]
-    }
-    pub const fn get_players_placed(&self) -> u16 {
-        self.players_placed
+        // More rounds and tables than fit in one word of tables to explore
+        let config = Config::new(3, 22, 2).unwrap();
+        let state = State::<u64>::new(config.clone()).unwrap();
+        assert!(state.solutions().next().unwrap().is_valid(&config));
     }
-    fn increment(&mut self) -> Result<(), FinishedStepping> {
-        self.players_placed += 1;
-        self.player_number += 1;
-        if self.player_number >= PLAYERS_PER_TABLE {
-            if let Ok(table) = Table::try_from(self.table as usize + 1) {
-                self.table = table;
-            } else if let Ok(round) = Round::try_from(self.round as usize + 1) {
-                self.round = round;
-                self.table = Table::Zero;
-            } else {
-                return Err(FinishedStepping {  });
+    fn run_df2<S: PlayerSet>(config: &Config) -> Vec<Vec<Vec<u8>>> {
+        let mut state = DF2::<S>::new(config.clone()).unwrap();
+        loop {
+            match state.step() {
+                Ok(()) => {}
+                Err(StepError::FinishedStepping(_)) => return state.get_schedule(),
+                Err(err) => panic!("{}", err),
             }
-            self.player_number = 0;
         }
-        Ok(())
     }
-    fn decrement(&mut self) -> Result<(), ExceededMaxBacktrack> {
-        self.players_placed -= 1;
-        if self.player_number == 0 {
-            //self.removed[self.round as usize][self.table as usize] = 0;
-            if let Ok(table) = Table::try_from((self.table as usize).wrapping_sub(1)) {
-                self.table = table;
-            } else if let Ok(round) = Round::try_from((self.round as usize).wrapping_sub(1)) {
-                self.round = round;
-                self.table = Table::Five;
-            } else {
-                return Err(ExceededMaxBacktrack{});
+    /// Steps depth first until the first finished state
+    fn run_state<S: PlayerSet>(config: &Config) -> State<S> {
+        let mut stack = vec![State::<S>::new(config.clone()).unwrap()];
+        let mut state2 = stack[0].clone();
+        loop {
+            let mut state = stack.pop().unwrap();
+            match state.step(&mut state2) {
+                Ok(Some(())) => {
+                    stack.push(state);
+                    stack.push(state2.clone());
+                }
+                Ok(None) => return state,
+                Err(DeadEnd {}) => {}
             }
-            self.player_number = PLAYERS_PER_TABLE - 1;
-        } else {
-            self.player_number -= 1
         }
-        Ok(())
     }
-    pub fn backtrack(&mut self) -> Result<(), ExceededMaxBacktrack> {
-        self.decrement()?;
-        assert_ne!(self.last_player(), PLAYER_COUNT as u8);
-        self.remove_last_player();
-        Ok(())
+    #[test]
+    fn player_set_widths_agree() {
+        let config = Config::new(4, 4, 3).unwrap();
+        let expected = run_df2::<u32>(&config);
+        assert_eq!(run_df2::<u64>(&config), expected);
+        assert_eq!(run_df2::<u128>(&config), expected);
+        assert_eq!(run_df2::<WidePlayerSet<2>>(&config), expected);
     }
-    pub fn step(&mut self) -> Result<(), StepError> {
-        self.increment()?;
-
-        let mut mask = self.get_mask(self.round, self.table);
-        while mask == 0 {
-            assert_eq!(self.last_player(), PLAYER_COUNT as u8);
-            self.backtrack()?;
-            mask = self.get_mask(self.round, self.table);
+    #[test]
+    fn more_than_32_players() {
+        let config = Config::new(4, 12, 3).unwrap();
+        assert!(DF2::<u32>::new(config.clone()).is_err());
+        assert!(State::<u32>::new(config.clone()).is_err());
+        let schedule = run_df2::<u64>(&config);
+        assert_eq!(run_df2::<WidePlayerSet<1>>(&config), schedule);
+        for round in schedule.iter() {
+            let mut players: Vec<u8> = round.iter().flatten().copied().collect();
+            players.sort_unstable();
+            assert_eq!(players, (0..36).collect::<Vec<u8>>());
         }
-        let player = mask.trailing_zeros() as u8;
-        self.apply_player(player);
-        Ok(())
     }
-    pub fn get_schedule(&self) -> [[[u8; 4]; TABLE_COUNT]; ROUND_COUNT] {
-        self.schedule
+    #[test]
+    fn wide_player_set() {
+        let set = WidePlayerSet::<3>::first_n(70) & !WidePlayerSet::single(3);
+        assert_eq!(set.count(), 69);
+        assert_eq!(set.lowest(), Some(0));
+        assert!(set.contains(69) && !set.contains(70) && !set.contains(3));
+        let high = WidePlayerSet::<3>::single(130) | WidePlayerSet::single(64);
+        assert_eq!(high.iter().collect::<Vec<_>>(), vec![64, 130]);
+        let encoded = bincode::serialize(&high).unwrap();
+        assert_eq!(
+            bincode::deserialize::<WidePlayerSet<3>>(&encoded).unwrap(),
+            high
+        );
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    fn init() {
-        let _ = env_logger::builder()
-            .filter_level(log::LevelFilter::Trace)
-            .is_test(true)
-            .try_init();
+    fn assert_valid(config: &Config, schedule: &[Vec<Vec<u8>>]) {
+        let mut met = std::collections::HashSet::new();
+        let mut visited = std::collections::HashSet::new();
+        let mut sit_outs = vec![0; config.player_count()];
+        for (round, tables) in config.rounds().zip(schedule.iter()) {
+            let mut players: Vec<u8> = tables.iter().flatten().copied().collect();
+            players.sort_unstable();
+            players.dedup();
+            assert_eq!(players.len(), config.seat_count());
+            let (playing, sitting_out): (Vec<usize>, Vec<usize>) = (0..config.player_count())
+                .filter(|&player| config.is_available(player, round))
+                .partition(|&player| players.binary_search(&(player as u8)).is_ok());
+            assert_eq!(playing.len(), players.len());
+            if let (Some(most), Some(fewest)) = (
+                sitting_out.iter().map(|&player| sit_outs[player]).max(),
+                playing.iter().map(|&player| sit_outs[player]).min(),
+            ) {
+                assert!(most <= fewest, "unfair sit-outs {:?}", sit_outs);
+            }
+            for (player, count) in sit_outs.iter_mut().enumerate() {
+                if players.binary_search(&(player as u8)).is_err() {
+                    *count += 1;
+                }
+            }
+            for (table, seats) in config.tables().zip(tables.iter()) {
+                assert_eq!(seats.len(), config.seats(round, table));
+                for &player in seats.iter() {
+                    assert!(visited.insert((player, table)));
+                    for &other in seats.iter().filter(|&&other| other > player) {
+                        assert!(met.insert((player, other)));
+                    }
+                }
+            }
+        }
+        for &(first, second) in config.forbidden_pairs().iter() {
+            assert!(!met.contains(&(first as u8, second as u8)));
+        }
+        for &(first, second) in config.must_meet_pairs().iter() {
+            assert!(met.contains(&(first as u8, second as u8)));
+        }
     }
+    #[test]
+    fn mixed_table_sizes() {
+        let config = Config::with_table_sizes(3, vec![2, 3, 3, 2, 3, 3]).unwrap();
+        assert_valid(&config, &run_df2::<u32>(&config));
 
-    fn expand_bitvec(mut x: u32) -> Vec<u8> {
-        let mut out = Vec::new();
-        while x != 0 {
-            let zeros = x.trailing_zeros() as u8;
-            x ^= 1 << zeros;
-            out.push(zeros);
-        }
+        let config = Config::with_round_table_sizes(vec![
+            vec![3, 3, 3, 3, 3, 3],
+            vec![4, 3, 3, 3, 3, 2],
+            vec![2, 3, 3, 3, 3, 4],
+        ])
+        .unwrap();
+        assert_valid(&config, &run_df2::<u32>(&config));
 
-        out
+        let finished = run_state::<u32>(&config);
+        assert_eq!(finished.get_players_played_count(), 3 * 18);
     }
-
     #[test]
-    fn test() {
-        let state = DF2::new();
-        let mask = expand_bitvec(state.get_mask(Round::One, Table::Zero));
-        assert_eq!(
-            mask,
-            vec![4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23]
-        );
+    fn sit_outs() {
+        let config = Config::new(4, 4, 3).unwrap().with_player_count(14).unwrap();
+        assert_eq!(config.sit_out_count(round(0)), 2);
+        assert_valid(&config, &run_df2::<u32>(&config));
+
+        let finished = run_state::<u32>(&config);
+        assert_eq!(finished.get_players_played_count(), 4 * 12);
+
+        let config = Config::new(5, 3, 3).unwrap();
+        assert!(config.clone().with_player_count(8).is_err());
+        assert!(config.with_player_count(256).is_err());
     }
     #[test]
-    fn test2() {
-        let state = DF2::from_slice(&[4, 8, 12, 16]).unwrap();
-        let mask = expand_bitvec(state.get_mask(Round::One, Table::One));
-        assert_eq!(
-            mask,
-            vec![0, 1, 2, 3, 9, 10, 11, 13, 14, 15, 17, 18, 19, 20, 21, 22, 23]
-        );
+    fn partial_attendance() {
+        let config = Config::new(4, 4, 3)
+            .unwrap()
+            .with_player_count(14)
+            .unwrap()
+            .with_available_rounds(12, &[2, 3])
+            .unwrap()
+            .with_available_rounds(13, &[0, 1, 2])
+            .unwrap();
+        assert!(!config.is_available(12, round(1)));
+        assert_eq!(config.sit_out_count(round(0)), 1);
+        assert_eq!(config.sit_out_count(round(2)), 2);
+        let mut state: DF2 = DF2::new(config.clone()).unwrap();
+        loop {
+            match state.step() {
+                Ok(()) => {}
+                Err(StepError::FinishedStepping(_)) => break,
+                Err(err) => panic!("{}", err),
+            }
+        }
+        assert_valid(&config, &state.get_schedule());
+        assert!(state.tables_visited(12) <= 2);
+        assert!(state.tables_visited(13) <= 3);
+        let visits: usize = (0..14).map(|player| state.tables_visited(player)).sum();
+        assert_eq!(visits, 4 * 12);
+
+        let finished = run_state::<u32>(&config);
+        assert_eq!(finished.get_players_played_count(), 4 * 12);
+        assert!(finished.tables_visited(12) <= 2);
+
+        assert!(config.clone().with_available_rounds(14, &[0]).is_err());
+        assert!(config.clone().with_available_rounds(0, &[4]).is_err());
+        let config = config.with_available_rounds(0, &[0, 2, 3]).unwrap();
+        assert!(config.with_available_rounds(1, &[0, 2, 3]).is_err());
     }
     #[test]
-    fn test3() {
-        let state = DF2::from_slice(&[4, 8, 12, 16, 0, 9, 13, 17]).unwrap();
-        let mask = expand_bitvec(state.get_mask(Round::One, Table::Two));
-        assert_eq!(mask, vec![1, 2, 3, 5, 6, 7, 14, 15, 18, 19, 20, 21, 22, 23]);
+    fn pinned_players() {
+        let config = Config::new(4, 4, 3)
+            .unwrap()
+            .with_pin(7, 2, 3)
+            .unwrap()
+            .with_pin(1, 1, 0)
+            .unwrap()
+            .with_pin(2, 1, 0)
+            .unwrap()
+            .with_pin(11, 1, 0)
+            .unwrap()
+            .with_pin(4, 0, 1)
+            .unwrap();
+        assert_eq!(config.pinned(round(1), table(0)), &[1, 2, 11]);
+        let schedule = run_df2::<u32>(&config);
+        assert_valid(&config, &schedule);
+        assert!(schedule[2][3].contains(&7));
+        assert_eq!(schedule[1][0], vec![1, 2, 11]);
+        assert!(schedule[0][1].contains(&4));
+
+        let finished = run_state::<u32>(&config);
+        assert_eq!(finished.get_players_played_count(), 4 * 12);
+        assert!(finished.progress.played_on_table[2][3].contains(7));
+
+        assert!(matches!(
+            config.clone().with_pin(7, 3, 3),
+            Err(ConfigError::Pin(PinError::TableRevisited { .. }))
+        ));
+        assert!(matches!(
+            config.clone().with_pin(7, 2, 0),
+            Err(ConfigError::Pin(PinError::TwiceInRound { .. }))
+        ));
+        assert!(matches!(
+            config.clone().with_pin(1, 3, 1).unwrap().with_pin(2, 3, 1),
+            Err(ConfigError::Pin(PinError::MeetTwice { .. }))
+        ));
+        assert!(matches!(
+            config.clone().with_pin(3, 1, 0),
+            Err(ConfigError::Pin(PinError::TableOverfull { .. }))
+        ));
+        assert!(matches!(
+            config.clone().with_pin(12, 3, 2),
+            Err(ConfigError::Pin(PinError::UnknownPlayer { .. }))
+        ));
+        assert!(config.with_pin(0, 4, 0).is_err());
     }
     #[test]
-    fn test4() {
-        let state = DF2::from_slice(&[4, 8, 12, 16, 0, 9, 13, 17, 1, 5, 14, 18, 20]).unwrap();
-        let mask = expand_bitvec(state.get_mask(Round::One, Table::Two));
-        assert_eq!(mask, vec![21, 22, 23]);
+    fn pair_constraints() {
+        let config = Config::new(4, 4, 3)
+            .unwrap()
+            .with_forbidden_pair(0, 1)
+            .unwrap()
+            .with_forbidden_pair(5, 2)
+            .unwrap()
+            .with_must_meet_pair(0, 11)
+            .unwrap()
+            .with_must_meet_pair(3, 7)
+            .unwrap();
+        assert_eq!(config.forbidden_pairs(), &[(0, 1), (2, 5)]);
+        assert_valid(&config, &run_df2::<u32>(&config));
+
+        let finished = run_state::<u32>(&config);
+        assert_eq!(finished.get_players_played_count(), 4 * 12);
+        assert!(!finished.progress.players_played_with[0].contains(1));
+        assert!(finished.progress.players_played_with[0].contains(11));
+        assert!(finished.progress.players_played_with[3].contains(7));
+
+        assert!(config.clone().with_must_meet_pair(1, 0).is_err());
+        assert!(config.clone().with_forbidden_pair(4, 4).is_err());
+        assert!(config.clone().with_forbidden_pair(4, 12).is_err());
+        assert!(matches!(
+            config.with_pin(2, 1, 1).unwrap().with_pin(5, 1, 1),
+            Err(ConfigError::Pin(PinError::ForbiddenPair { .. }))
+        ));
     }
     #[test]
-    fn run_successful() {
-        init();
-        let mut state = DF2::from_slice(&[
-            4, 8, 12, 16, 0, 9, 13, 20, 1, 5, 17, 21, 2, 6, 18, 22, 3, 10, 14, 23, 7, 11, 15, 19,
-            5, 9, 14, 18, 3, 15, 16, 22, 2, 7, 12, 20, 1, 8, 19, 23, 6, 11, 13, 21, 0, 4, 10, 17,
-            6, 10, 19, 20, 2, 11, 14, 17, 4, 15, 18, 23, 0, 7, 16, 21, 1, 9, 12, 22, 3, 5, 8, 13,
-            7, 13, 17, 23, 10, 12, 18, 21, 0, 14, 19, 22, 3, 4, 11,
-        ])
-        .unwrap();
-        let mask = expand_bitvec(state.get_mask(state.round, state.table));
-        assert_eq!(mask, vec![9, 20]);
+    fn uneven_rounds_rejected() {
+        assert!(Config::with_round_table_sizes(vec![vec![4, 4], vec![5, 4]]).is_err());
+        assert!(Config::with_round_table_sizes(vec![vec![4, 4], vec![4, 2, 2]]).is_err());
+        assert!(Config::with_table_sizes(3, vec![4, 0, 4]).is_err());
+        assert!(Config::with_round_table_sizes(vec![]).is_err());
     }
     #[quickcheck_macros::quickcheck]
     fn forwards_does_not_include_removed(steps: u16) {
-        let mut state = DF2::new();
-        for _ in 0..steps {
+        let mut state: DF2 = DF2::default();
+        // Round 0 is fixed, so there is nothing to backtrack until a step places a player
+        for _ in 0..=steps {
             state.step().unwrap();
         }
         let last_player = state.last_player();
@@ -357,7 +2582,7 @@ mod tests {
     }
     #[quickcheck_macros::quickcheck]
     fn does_not_repeat(steps: u16) {
-        let mut state = DF2::new();
+        let mut state: DF2 = DF2::default();
         for _ in 0..steps {
             let old_schedule = state.get_schedule();
             state.step().unwrap();
@@ -366,159 +2591,424 @@ mod tests {
     }
 }
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
-pub struct State {
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct State<S = u32> {
+    /// Shared by every state cloned from this one
+    config: Arc<Config>,
+    progress: Progress<S>,
+}
+
+/// Everything in a `State` but its config, which is all that searches holding many states from
+/// the same config save of each
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+struct Progress<S> {
     tables_to_explore: ToExplore,
-    players_played_count: u8,
-    empty_table_count: u8,
-    players_played_with: [u32; PLAYER_COUNT],
-    played_in_round: [u32; ROUND_COUNT],
-    played_on_table: [[u32; TABLE_COUNT]; ROUND_COUNT],
-    potential_on_table: [[u32; TABLE_COUNT]; ROUND_COUNT],
-    played_on_table_total: [u32; TABLE_COUNT],
+    players_played_count: u16,
+    empty_table_count: u16,
+    players_played_with: Vec<S>,
+    played_in_round: Vec<S>,
+    played_on_table: Vec<Vec<S>>,
+    potential_on_table: Vec<Vec<S>>,
+    played_on_table_total: Vec<S>,
+    /// Players each player may never share a table with
+    forbidden: Vec<S>,
+    /// Whether rounds are kept in order of the players at table 0
+    order_rounds: bool,
 }
 
-impl std::fmt::Display for State {
+impl<S: PlayerSet> std::fmt::Display for State<S> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.format_schedule(f)
     }
 }
 
-impl State {
-    pub fn new() -> Self {
-        let potential_on_table = [[PLAYER_MASK; TABLE_COUNT]; ROUND_COUNT];
-        let mut state = Self {
-            tables_to_explore: ToExplore::filled(),
+impl<S: PlayerSet> Default for State<S> {
+    fn default() -> Self {
+        Self::new(Config::default()).unwrap()
+    }
+}
+
+impl<S: PlayerSet> State<S> {
+    pub fn new(config: Config) -> Result<Self, ConfigError> {
+        Self::with_round_ordering(config, true)
+    }
+
+    /// Starts a search which does not keep rounds in order, for use with `step_unique` and
+    /// `bstep_unique`. Rejecting isomorphic states breaks the same symmetry, so doing both would
+    /// lose solutions
+    pub fn new_unordered(config: Config) -> Result<Self, ConfigError> {
+        Self::with_round_ordering(config, false)
+    }
+
+    fn with_round_ordering(config: Config, order_rounds: bool) -> Result<Self, ConfigError> {
+        let mut state = Self::pinned(config, order_rounds)?;
+        // Players can only be relabelled to fill round 0 in order when they are interchangeable
+        if state.config.players_interchangeable() {
+            let mut player = 0;
+            for table in state.config.tables() {
+                for _ in 0..state.config.seats(Round::ZERO, table) {
+                    state.apply_player(Round::ZERO, table, player);
+                    player += 1;
+                }
+            }
+        }
+        Ok(state)
+    }
+
+    /// Starts with only the pinned players placed
+    fn pinned(config: Config, order_rounds: bool) -> Result<Self, ConfigError> {
+        config.check_capacity::<S>()?;
+        let potential_on_table = config
+            .rounds()
+            .map(|round| vec![config.available(round); config.table_count()])
+            .collect();
+        let progress = Progress {
+            tables_to_explore: ToExplore::filled(&config),
             players_played_count: 0,
-            empty_table_count: (ROUND_COUNT * TABLE_COUNT) as u8,
-            players_played_with: [0; PLAYER_COUNT],
-            played_in_round: [0; ROUND_COUNT],
-            played_on_table: [[0; TABLE_COUNT]; ROUND_COUNT],
+            empty_table_count: (config.round_count() * config.table_count()) as u16,
+            players_played_with: vec![S::empty(); config.player_count()],
+            played_in_round: vec![S::empty(); config.round_count()],
+            played_on_table: vec![vec![S::empty(); config.table_count()]; config.round_count()],
             potential_on_table,
-            played_on_table_total: [0; TABLE_COUNT],
+            played_on_table_total: vec![S::empty(); config.table_count()],
+            forbidden: (0..config.player_count())
+                .map(|player| config.forbidden_with(player))
+                .collect(),
+            order_rounds,
         };
-        let mut player = 0;
-        for table in TABLES {
-            for _ in 0..PLAYERS_PER_TABLE {
-                state.apply_player(Round::Zero, table, player);
-                player += 1;
+        let mut state = Self {
+            config: Arc::new(config),
+            progress,
+        };
+        for round in state.config.rounds() {
+            for table in state.config.tables() {
+                for seat in 0..state.config.pinned(round, table).len() {
+                    let player = state.config.pinned(round, table)[seat];
+                    state.apply_player(round, table, player);
+                }
+            }
+        }
+        Ok(state)
+    }
+
+    /// Continues from a schedule, which may be partly filled. Rounds are not kept in order, so
+    /// any schedule that keeps to the config is accepted
+    pub fn from_schedule(config: Config, schedule: &Schedule) -> Result<Self, InitialisationError> {
+        let mut state = Self::pinned(config, false)?;
+        let config = state.config.clone();
+        for (round, tables) in config.rounds().zip(schedule.rounds().iter()) {
+            for (table, seated) in config.tables().zip(tables.iter()) {
+                let pinned = config.pinned(round, table);
+                if let Some(&player) = pinned
+                    .iter()
+                    .find(|&&player| !seated.contains(&(player as u8)))
+                {
+                    return Err(InitialisationError::PinMissing {
+                        round: round.index(),
+                        table: table.index(),
+                        player,
+                    });
+                }
+                for player in seated.iter().map(|&player| player as usize) {
+                    if pinned.contains(&player) {
+                        continue;
+                    }
+                    let played = state.progress.played_on_table[round.index()][table.index()];
+                    if player >= config.player_count()
+                        || played.count() as usize >= config.seats(round, table)
+                        || played.contains(player)
+                        || !state.progress.potential_on_table[round.index()][table.index()]
+                            .contains(player)
+                        || !state.can_play_with_players_in_game(round, table, player)
+                    {
+                        return Err(InitialisationError::Unplaceable {
+                            round: round.index(),
+                            table: table.index(),
+                            player,
+                        });
+                    }
+                    state.apply_player(round, table, player);
+                }
             }
         }
-        //state.apply_player(Round::One, Table::Zero, 4);
-        //state.apply_player(Round::One, Table::Zero, 8);
-        //state.apply_player(Round::One, Table::Zero, 12);
-        //state.apply_player(Round::One, Table::Zero, 16);
-        state
+        if !state.constraints_hold() {
+            return Err(InitialisationError::ConstraintsBroken);
+        }
+        Ok(state)
+    }
+
+    pub fn get_config(&self) -> &Config {
+        &self.config
     }
 
     fn can_play_with_players_in_game(&self, round: Round, table: Table, player: usize) -> bool {
-        self.players_played_with[player] & self.played_on_table[round as usize][table as usize] == 0
+        let excluded = self.progress.players_played_with[player] | self.progress.forbidden[player];
+        (excluded & self.progress.played_on_table[round.index()][table.index()]).is_empty()
     }
 
     fn game_full(&mut self, round: Round, table: Table) {
-        self.empty_table_count = self.empty_table_count.checked_sub(1).unwrap();
-        self.tables_to_explore.remove(round, table);
-        self.potential_on_table[round as usize][table as usize] =
-            self.played_on_table[round as usize][table as usize];
-        if table == Table::Zero {
-            let lowest_player =
-                self.played_on_table[round as usize][table as usize].trailing_ones();
-            let mask = !((1 << lowest_player) - 1);
-            for round in (round as usize + 1)..ROUND_COUNT {
-                self.potential_on_table[round as usize][table as usize] &= mask;
+        self.progress.empty_table_count = self.progress.empty_table_count.checked_sub(1).unwrap();
+        self.progress
+            .tables_to_explore
+            .remove(&self.config, round, table);
+        self.progress.potential_on_table[round.index()][table.index()] =
+            self.progress.played_on_table[round.index()][table.index()];
+        // Rounds can only be reordered when no player is singled out and everyone plays every
+        // round, as otherwise the order decides who sits out next
+        if table == Table::ZERO
+            && self.progress.order_rounds
+            && self.config.rounds_interchangeable()
+        {
+            let lowest_player = (!self.progress.played_on_table[round.index()][table.index()])
+                .lowest()
+                .unwrap_or(S::CAPACITY);
+            let mask = !S::first_n(lowest_player);
+            for round in (round.index() + 1)..self.config.round_count() {
+                self.progress.potential_on_table[round][table.index()] &= mask;
             }
         }
     }
 
     fn apply_player(&mut self, round: Round, table: Table, player: usize) {
-        if round as usize >= ROUND_COUNT || table as usize >= TABLE_COUNT || player >= PLAYER_COUNT
-        {
+        if player >= self.config.player_count() {
             unreachable!();
         }
 
         debug_assert!(self.can_play_with_players_in_game(round, table, player));
 
-        self.players_played_count += 1;
-        let player_mask: u32 = 1 << player;
-        let remove_player_mask: u32 = !player_mask;
-        for ptr in self.potential_on_table.iter_mut() {
+        self.progress.players_played_count += 1;
+        let player_mask = S::single(player);
+        let remove_player_mask = !player_mask;
+        for ptr in self.progress.potential_on_table.iter_mut() {
             // Remove player from the table in other rounds
-            ptr[table as usize] &= remove_player_mask;
+            ptr[table.index()] &= remove_player_mask;
         }
-        for ptr in self.potential_on_table[round as usize].iter_mut() {
+        for ptr in self.progress.potential_on_table[round.index()].iter_mut() {
             // Remove player from other tables in the same round
             *ptr &= remove_player_mask;
         }
 
         // Add player to played in round
-        debug_assert_eq!(self.played_in_round[round as usize] & player_mask, 0);
-        self.played_in_round[round as usize] |= player_mask;
+        debug_assert!(!self.progress.played_in_round[round.index()].contains(player));
+        self.progress.played_in_round[round.index()] |= player_mask;
         // Add player to played on table
-        debug_assert_eq!(self.played_on_table_total[table as usize] & player_mask, 0);
-        self.played_on_table_total[table as usize] |= player_mask;
+        debug_assert!(!self.progress.played_on_table_total[table.index()].contains(player));
+        self.progress.played_on_table_total[table.index()] |= player_mask;
 
-        let mut other_players = self.played_on_table[round as usize][table as usize];
-        debug_assert_eq!(other_players & player_mask, 0);
+        let other_players = self.progress.played_on_table[round.index()][table.index()];
+        debug_assert!(!other_players.contains(player));
         // Remove players current player has previously played with from tables potential
-        self.potential_on_table[round as usize][table as usize] &=
-            !self.players_played_with[player];
+        self.progress.potential_on_table[round.index()][table.index()] &=
+            !(self.progress.players_played_with[player] | self.progress.forbidden[player]);
         // Add other players on table to current players played with list
-        self.players_played_with[player] |= other_players;
-        while other_players != 0 {
-            let other_player = other_players.trailing_zeros() as usize;
-            other_players &= !(1 << other_player);
+        self.progress.players_played_with[player] |= other_players;
+        for other_player in other_players.iter() {
             // Add current player to each other players played with list
-            self.players_played_with[other_player] |= player_mask;
+            self.progress.players_played_with[other_player] |= player_mask;
         }
 
-        debug_assert_eq!(
-            self.potential_on_table[round as usize][table as usize] & player_mask,
-            0
-        );
-        self.potential_on_table[round as usize][table as usize] |= player_mask;
-        debug_assert_eq!(
-            self.played_on_table[round as usize][table as usize] & player_mask,
-            0
+        debug_assert!(
+            !self.progress.potential_on_table[round.index()][table.index()].contains(player)
         );
-        self.played_on_table[round as usize][table as usize] |= player_mask;
-        if self.played_on_table[round as usize][table as usize].count_ones()
-            == PLAYERS_PER_TABLE as u32
+        self.progress.potential_on_table[round.index()][table.index()] |= player_mask;
+        debug_assert!(!self.progress.played_on_table[round.index()][table.index()].contains(player));
+        self.progress.played_on_table[round.index()][table.index()] |= player_mask;
+        if self.progress.played_on_table[round.index()][table.index()].count()
+            == self.config.seats(round, table) as u32
         {
             self.game_full(round, table);
         }
         debug_assert!(
-            self.played_on_table[round as usize][table as usize].count_ones()
-                <= PLAYERS_PER_TABLE as u32
+            self.progress.played_on_table[round.index()][table.index()].count()
+                <= self.config.seats(round, table) as u32
         );
     }
 
     pub fn get_available_count(&self) -> u32 {
         let mut total = 0;
-        for round in ROUNDS {
-            for table in TABLES {
-                total += self.potential_on_table[round as usize][table as usize].count_ones();
+        for potential_in_round in self.progress.potential_on_table.iter() {
+            for potential in potential_in_round.iter() {
+                total += potential.count();
             }
         }
         total
     }
 
-    pub fn get_players_played_count(&self) -> u8 {
-        self.players_played_count
+    pub fn get_players_played_count(&self) -> u16 {
+        self.progress.players_played_count
+    }
+
+    /// Whether every table has been filled and checked, so `bstep` has nothing left to do
+    pub fn is_complete(&self) -> bool {
+        self.progress.empty_table_count == 0
+    }
+
+    /// Calls `f` with every player placed in this state but not in `other`
+    pub(crate) fn for_each_player_not_in<F>(&self, other: &Self, mut f: F)
+    where
+        F: FnMut(Round, Table, usize),
+    {
+        for round in self.config.rounds() {
+            for table in self.config.tables() {
+                let played = self.progress.played_on_table[round.index()][table.index()]
+                    & !other.progress.played_on_table[round.index()][table.index()];
+                for player in played.iter() {
+                    f(round, table, player);
+                }
+            }
+        }
+    }
+
+    /// Players placed at each table, indexed by round then table
+    pub fn get_schedule(&self) -> Vec<Vec<Vec<u8>>> {
+        self.progress
+            .played_on_table
+            .iter()
+            .map(|tables| {
+                tables
+                    .iter()
+                    .map(|played| played.iter().map(|player| player as u8).collect())
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn schedule(&self) -> Schedule {
+        Schedule::new(self.get_schedule())
+    }
+
+    /// Whether every array matches the config, for checking states read from a checkpoint
+    pub(crate) fn fits_config(&self) -> bool {
+        let config = &self.config;
+        let round_count = config.round_count();
+        let table_count = config.table_count();
+        let player_count = config.player_count();
+        let fits_grid = |grid: &Vec<Vec<S>>| {
+            grid.len() == round_count && grid.iter().all(|tables| tables.len() == table_count)
+        };
+        config.check_capacity::<S>().is_ok()
+            && self.progress.tables_to_explore.fits_config(config)
+            && self.progress.players_played_with.len() == player_count
+            && self.progress.played_in_round.len() == round_count
+            && fits_grid(&self.progress.played_on_table)
+            && fits_grid(&self.progress.potential_on_table)
+            && self.progress.played_on_table_total.len() == table_count
+            && self.progress.forbidden.len() == player_count
+            && self
+                .progress
+                .forbidden
+                .iter()
+                .enumerate()
+                .all(|(player, &forbidden)| forbidden == config.forbidden_with(player))
+            && self.fits_player_sets()
+    }
+
+    /// Whether the player sets agree with the players placed at each table, with nobody placed
+    /// twice in a round or at a table and only players in the config able to be placed
+    fn fits_player_sets(&self) -> bool {
+        let config = &self.config;
+        let players = S::first_n(config.player_count());
+        let mut played_in_round = vec![S::empty(); config.round_count()];
+        let mut played_on_table_total = vec![S::empty(); config.table_count()];
+        let mut players_played_with = vec![S::empty(); config.player_count()];
+        for (round, tables) in self.progress.played_on_table.iter().enumerate() {
+            for (table, &played) in tables.iter().enumerate() {
+                let potential = self.progress.potential_on_table[round][table];
+                if !(potential & !players).is_empty()
+                    || played & potential != played
+                    || !(played & played_in_round[round]).is_empty()
+                    || !(played & played_on_table_total[table]).is_empty()
+                {
+                    return false;
+                }
+                played_in_round[round] |= played;
+                played_on_table_total[table] |= played;
+                for player in played.iter() {
+                    players_played_with[player] |= played & !S::single(player);
+                }
+            }
+        }
+        played_in_round == self.progress.played_in_round
+            && played_on_table_total == self.progress.played_on_table_total
+            && players_played_with == self.progress.players_played_with
+    }
+
+    /// Every schedule reachable from this state
+    pub fn solutions(self) -> StateSolutions<S> {
+        StateSolutions::new(self)
+    }
+
+    /// Number of different tables a player has been seated at, which is less than the number of
+    /// tables for players who sit out or miss rounds
+    pub fn tables_visited(&self, player: usize) -> usize {
+        self.progress
+            .played_on_table_total
+            .iter()
+            .filter(|played| played.contains(player))
+            .count()
+    }
+
+    /// Checks the constraints which can only be decided across several tables
+    fn constraints_hold(&self) -> bool {
+        self.sit_outs_fair() && self.must_meet_pairs_can_meet()
+    }
+
+    /// Checks that every must-meet pair has met, or could still meet at some table
+    fn must_meet_pairs_can_meet(&self) -> bool {
+        self.config
+            .must_meet_pairs()
+            .iter()
+            .all(|&(first, second)| {
+                let pair = S::single(first) | S::single(second);
+                self.progress
+                    .potential_on_table
+                    .iter()
+                    .flatten()
+                    .any(|&potential| potential & pair == pair)
+            })
+    }
+
+    /// Checks that sit-outs are spread evenly over every round filled so far, up to the first
+    /// round that still has empty seats
+    fn sit_outs_fair(&self) -> bool {
+        if !self.config.has_sit_outs() {
+            return true;
+        }
+        let mut sit_outs = sit_out::SitOuts::new(self.config.player_count());
+        for (round, &played) in self
+            .config
+            .rounds()
+            .zip(self.progress.played_in_round.iter())
+        {
+            if played.count() as usize != self.config.seat_count() {
+                break;
+            }
+            if !sit_outs.is_fair(self.config.available::<S>(round), played) {
+                return false;
+            }
+            sit_outs.record(played);
+        }
+        true
     }
 
-    pub fn find_hidden_singles(&mut self) -> Result<(), ()> {
-        for round in ROUNDS {
-            let mut potential_in_row = !self.played_in_round[round as usize];
-            'loop_bits_round: while potential_in_row != 0 {
-                let player = potential_in_row.trailing_zeros() as usize;
-                if player >= PLAYER_COUNT {
+    pub fn find_hidden_singles(&mut self) -> Result<(), DeadEnd> {
+        // Players sitting out a round need not be seated in it, nor visit every table
+        if self.config.has_sit_outs() {
+            return Ok(());
+        }
+        for round in self.config.rounds() {
+            let potential_in_row =
+                self.config.available::<S>(round) & !self.progress.played_in_round[round.index()];
+            'loop_bits_round: for player in potential_in_row.iter() {
+                if player >= self.config.player_count() {
                     break;
                 }
-                let player_bit: u32 = 1 << player;
-                potential_in_row &= !player_bit;
+                let player_bit = S::single(player);
                 let mut only_position = None;
-                for table in TABLES {
-                    if self.potential_on_table[round as usize][table as usize] & player_bit != 0 {
+                for table in self.config.tables() {
+                    if self.progress.potential_on_table[round.index()][table.index()]
+                        .contains(player)
+                    {
                         if self.can_play_with_players_in_game(round, table, player) {
                             if only_position.is_none() {
                                 only_position = Some(table);
@@ -526,7 +3016,8 @@ impl State {
                                 continue 'loop_bits_round;
                             }
                         } else {
-                            self.potential_on_table[round as usize][table as usize] &= !player_bit;
+                            self.progress.potential_on_table[round.index()][table.index()] &=
+                                !player_bit;
                         }
                     }
                 }
@@ -534,23 +3025,33 @@ impl State {
                     self.apply_player(round, table, player);
                 } else {
                     // No game in round can have player
-                    return Err(());
+                    return Err(DeadEnd {});
                 }
             }
         }
 
-        for table in TABLES {
-            let mut potential_in_column = !self.played_on_table_total[table as usize];
-            'loop_bits_table: while potential_in_column != 0 {
-                let player = potential_in_column.trailing_zeros() as usize;
-                if player >= PLAYER_COUNT {
+        if self.config.round_count() != self.config.table_count() {
+            // Players do not visit every table
+            return Ok(());
+        }
+        // Only players attending every round visit every table
+        let full_attendance = self.config.rounds().fold(
+            S::first_n(self.config.player_count()),
+            |attending, round| attending & self.config.available(round),
+        );
+        for table in self.config.tables() {
+            let potential_in_column =
+                full_attendance & !self.progress.played_on_table_total[table.index()];
+            'loop_bits_table: for player in potential_in_column.iter() {
+                if player >= self.config.player_count() {
                     break;
                 }
-                let player_bit = 1 << player;
-                potential_in_column &= !player_bit;
+                let player_bit = S::single(player);
                 let mut only_position = None;
-                for round in ROUNDS {
-                    if self.potential_on_table[round as usize][table as usize] & player_bit != 0 {
+                for round in self.config.rounds() {
+                    if self.progress.potential_on_table[round.index()][table.index()]
+                        .contains(player)
+                    {
                         if self.can_play_with_players_in_game(round, table, player) {
                             if only_position.is_none() {
                                 only_position = Some(round);
@@ -558,7 +3059,8 @@ impl State {
                                 continue 'loop_bits_table;
                             }
                         } else {
-                            self.potential_on_table[round as usize][table as usize] &= !player_bit;
+                            self.progress.potential_on_table[round.index()][table.index()] &=
+                                !player_bit;
                         }
                     }
                 }
@@ -566,7 +3068,7 @@ impl State {
                     self.apply_player(round, table, player);
                 } else {
                     // No game on table can have player
-                    return Err(());
+                    return Err(DeadEnd {});
                 }
             }
         }
@@ -574,19 +3076,20 @@ impl State {
     }
 
     #[inline(never)]
-    pub fn step(&mut self, state2: &mut Self) -> Result<Option<()>, ()> {
+    pub fn step(&mut self, state2: &mut Self) -> Result<Option<()>, DeadEnd> {
         //self.find_hidden_singles()?;
 
         let mut lowest: Option<(u8, Round, Table)> = None;
-        let mut to_explore = self.tables_to_explore;
-        while let Some((round, table)) = to_explore.pop() {
+        let mut to_explore = self.progress.tables_to_explore.clone();
+        while let Some((round, table)) = to_explore.pop(&self.config) {
             let fixed_player_count =
-                self.played_on_table[round as usize][table as usize].count_ones() as u8;
-            match fixed_player_count.cmp(&(PLAYERS_PER_TABLE as u8)) {
+                self.progress.played_on_table[round.index()][table.index()].count() as u8;
+            let seats = self.config.seats(round, table) as u8;
+            match fixed_player_count.cmp(&seats) {
                 core::cmp::Ordering::Less => {
-                    let potential = self.potential_on_table[round as usize][table as usize];
-                    let potential_count = potential.count_ones() as u8;
-                    match potential_count.cmp(&(PLAYERS_PER_TABLE as u8)) {
+                    let potential = self.progress.potential_on_table[round.index()][table.index()];
+                    let potential_count = potential.count() as u8;
+                    match potential_count.cmp(&seats) {
                         core::cmp::Ordering::Greater => {
                             lowest = Some(if let Some(lowest) = lowest {
                                 if potential_count < lowest.0 {
@@ -599,22 +3102,20 @@ impl State {
                             });
                         }
                         core::cmp::Ordering::Equal => {
-                            let mut potential =
-                                potential & !self.played_on_table[round as usize][table as usize];
-                            while potential != 0 {
-                                let player = potential.trailing_zeros() as usize;
-                                potential &= !(1 << player);
+                            let mut potential = potential
+                                & !self.progress.played_on_table[round.index()][table.index()];
+                            while let Some(player) = potential.pop_lowest() {
                                 if self.can_play_with_players_in_game(round, table, player) {
                                     self.apply_player(round, table, player);
                                 } else {
                                     // Cannot fill game
-                                    return Err(());
+                                    return Err(DeadEnd {});
                                 }
                             }
                         }
                         core::cmp::Ordering::Less => {
                             // Not enough potential to fill game
-                            return Err(());
+                            return Err(DeadEnd {});
                         }
                     }
                 }
@@ -624,74 +3125,118 @@ impl State {
                 }
                 core::cmp::Ordering::Greater => {
                     unreachable!(); // Shouldn't be possible
-                    return Err(());
                 }
             }
         }
+        if !self.constraints_hold() {
+            return Err(DeadEnd {});
+        }
         if let Some((_, round, table)) = lowest {
-            let potential = self.potential_on_table[round as usize][table as usize]
-                & !self.played_on_table[round as usize][table as usize];
-            let mut temp = potential;
-            'played_iter: while temp != 0 {
-                let player = temp.trailing_zeros() as usize;
-                let player_bit = 1 << player;
-                temp &= !player_bit;
+            let potential = self.progress.potential_on_table[round.index()][table.index()]
+                & !self.progress.played_on_table[round.index()][table.index()];
+            'played_iter: for player in potential.iter() {
+                let player_bit = S::single(player);
                 if self.can_play_with_players_in_game(round, table, player) {
-                    *state2 = *self;
-                    self.potential_on_table[round as usize][table as usize] &= !player_bit;
+                    state2.clone_from(self);
+                    self.progress.potential_on_table[round.index()][table.index()] &= !player_bit;
                     state2.apply_player(round, table, player);
                     return Ok(Some(()));
                 } else {
-                    self.potential_on_table[round as usize][table as usize] &= !player_bit;
+                    self.progress.potential_on_table[round.index()][table.index()] &= !player_bit;
                     continue 'played_iter;
                 }
             }
-            return Err(());
+            return Err(DeadEnd {});
         }
         Ok(None)
     }
 
+    /// Like `step`, but skips branches isomorphic to a state the filter has already seen
+    pub fn step_unique(
+        &mut self,
+        state2: &mut Self,
+        filter: &mut IsomorphismFilter,
+    ) -> Result<Option<()>, UniqueStepError> {
+        self.check_unordered()?;
+        loop {
+            match self.step(state2)? {
+                Some(()) if !filter.insert(state2) => {}
+                result => return Ok(result),
+            }
+        }
+    }
+
+    /// Like `bstep`, but only passes on states not isomorphic to one the filter has already seen
+    pub fn bstep_unique<C>(
+        &mut self,
+        callback: &mut C,
+        filter: &mut IsomorphismFilter,
+    ) -> Result<(), RoundsOrdered>
+    where
+        C: FnMut(&Self),
+    {
+        self.check_unordered()?;
+        self.bstep(&mut |state: &Self| {
+            if filter.insert(state) {
+                callback(state)
+            }
+        });
+        Ok(())
+    }
+
+    /// Isomorphism rejection would lose solutions from a search that keeps rounds in order
+    fn check_unordered(&self) -> Result<(), RoundsOrdered> {
+        if self.progress.order_rounds {
+            Err(RoundsOrdered {})
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn bstep<C>(&mut self, callback: &mut C)
     where
         C: FnMut(&Self),
     {
-        let mut to_explore = self.tables_to_explore;
-        if let Some((round, table)) = to_explore.pop() {
+        if let Some((round, table)) = self.progress.tables_to_explore.first(&self.config) {
             let fixed_player_count =
-                self.played_on_table[round as usize][table as usize].count_ones() as u8;
-            match fixed_player_count.cmp(&(PLAYERS_PER_TABLE as u8)) {
+                self.progress.played_on_table[round.index()][table.index()].count() as u8;
+            let seats = self.config.seats(round, table) as u8;
+            match fixed_player_count.cmp(&seats) {
                 core::cmp::Ordering::Less => {
-                    let potential = self.potential_on_table[round as usize][table as usize];
-                    let potential_count = potential.count_ones() as u8;
-                    match potential_count.cmp(&(PLAYERS_PER_TABLE as u8)) {
+                    let potential = self.progress.potential_on_table[round.index()][table.index()];
+                    let potential_count = potential.count() as u8;
+                    match potential_count.cmp(&seats) {
                         core::cmp::Ordering::Greater => {
                             // Find players
 
-                            let mut to_add =
-                                potential & !self.played_on_table[round as usize][table as usize];
-                            while to_add != 0 {
-                                let player = to_add.trailing_zeros() as usize;
-                                to_add &= !(1 << player);
+                            let mut to_add = potential
+                                & !self.progress.played_on_table[round.index()][table.index()];
+                            while let Some(player) = to_add.pop_lowest() {
                                 if self.can_play_with_players_in_game(round, table, player) {
-                                    let mut new = *self;
+                                    let mut new = self.clone();
 
                                     // Make it remove all lower numbers so that lowest player is always added first
                                     // Ensures that all generated solutions are unique
-                                    new.potential_on_table[round as usize][table as usize] &=
-                                        !((1 << player) - 1);
+                                    // Players already seated stay, as the table is filled from them
+                                    new.progress.potential_on_table[round.index()]
+                                        [table.index()] &= !S::first_n(player)
+                                        | self.progress.played_on_table[round.index()]
+                                            [table.index()];
                                     new.apply_player(round, table, player);
-                                    callback(&new);
+                                    if new.constraints_hold() {
+                                        callback(&new);
+                                    }
                                 } else {
-                                    unreachable!();
+                                    // Partial schedules read with `from_schedule` can leave
+                                    // players in potential who have met someone at the table
+                                    continue;
                                 }
                             }
                         }
                         core::cmp::Ordering::Equal => {
-                            let mut potential =
-                                potential & !self.played_on_table[round as usize][table as usize];
-                            while potential != 0 {
-                                let player = potential.trailing_zeros() as usize;
-                                potential &= !(1 << player);
+                            let mut potential = potential
+                                & !self.progress.played_on_table[round.index()][table.index()];
+                            while let Some(player) = potential.pop_lowest() {
                                 if self.can_play_with_players_in_game(round, table, player) {
                                     self.apply_player(round, table, player);
                                 } else {
@@ -700,11 +3245,12 @@ impl State {
                                 }
                             }
 
-                            callback(self);
+                            if self.constraints_hold() {
+                                callback(self);
+                            }
                         }
                         core::cmp::Ordering::Less => {
                             // Not enough potential to fill game
-                            return;
                         }
                     }
                 }
@@ -713,7 +3259,9 @@ impl State {
                 }
                 core::cmp::Ordering::Equal => {
                     self.game_full(round, table);
-                    callback(self);
+                    if self.constraints_hold() {
+                        callback(self);
+                    }
                 }
             }
         } else {
@@ -723,66 +3271,17 @@ impl State {
     }
 
     pub fn format_schedule<W: core::fmt::Write>(&self, output: &mut W) -> core::fmt::Result {
-        fn base_10_length(n: usize) -> usize {
-            (1..)
-                .try_fold(n, |acc, i| if acc >= 10 { Ok(acc / 10) } else { Err(i) })
-                .err()
-                .unwrap_or(0)
-        }
-        output.write_str("     ")?;
-        for table in 0..TABLE_COUNT {
-            let now = table + 1;
-            output.write_char('|')?;
-            for _ in 0..(3 - base_10_length(now)) {
-                output.write_char(' ')?;
-            }
-            output.write_fmt(format_args!("{}", now))?;
-            output.write_str("  ")?;
-        }
-
-        for round in 0..ROUND_COUNT {
-            output.write_str("\n-----")?;
-            for _ in 0..TABLE_COUNT {
-                output.write_char('+')?;
-                output.write_str("-----")?;
-            }
-            for i in 0..PLAYERS_PER_TABLE + 1 {
-                if i == (PLAYERS_PER_TABLE + 1) / 2 {
-                    output.write_char('\n')?;
-                    let now = round + 1;
-                    for _ in 0..(3 - base_10_length(now)) {
-                        output.write_char(' ')?;
-                    }
-                    output.write_fmt(format_args!("{}", now))?;
-                    output.write_str("  ")?;
-                } else {
-                    output.write_str("\n     ")?;
-                }
-                'table: for table in 0..TABLE_COUNT {
-                    output.write_char('|')?;
-                    let mut counter = 0;
-                    let mut temp = self.played_on_table[round as usize][table as usize];
-                    while temp != 0 {
-                        let trailing_zeros = temp.trailing_zeros() as usize;
-                        let player = trailing_zeros;
-                        let player_bit = 1 << trailing_zeros;
-                        temp &= !player_bit;
-                        if counter == i {
-                            let now = player;
-                            for _ in 0..(3 - base_10_length(now)) {
-                                output.write_char(' ')?;
-                            }
-                            output.write_fmt(format_args!("{}", now))?;
-                            output.write_str("  ")?;
-                            continue 'table;
-                        }
-                        counter += 1;
-                    }
-
-                    output.write_str("     ")?;
-                }
-            }
-        }
-        Ok(())
+        let max_seats: Vec<usize> = self
+            .config
+            .rounds()
+            .map(|round| self.config.max_seats(round))
+            .collect();
+        export::write_grid(
+            output,
+            &self.get_schedule(),
+            self.config.table_count(),
+            &max_seats,
+            |_, _, _, player| player.to_string(),
+        )
     }
 }
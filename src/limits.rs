@@ -0,0 +1,178 @@
+//! Stopping searches early, on running out of time or steps or when asked to from elsewhere
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{Schedule, SearchStats, SearchStep};
+
+/// Steps between checks of the clock and the cancellation token
+const CLOCK_INTERVAL: u64 = 1 << 10;
+
+/// What came of searching for a single schedule
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SearchOutcome {
+    Found(Schedule),
+    /// The whole search was made without finding a schedule
+    Infeasible,
+    /// The rest of the search was made without finding another schedule, after finding some
+    /// since the search was made or resumed
+    Exhausted,
+    /// Stopped early, with the partial schedule with the most players placed
+    LimitReached {
+        limit: Limit,
+        best_partial: Option<Schedule>,
+    },
+}
+
+/// Which of the `SearchLimits` stopped a search
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Limit {
+    Time,
+    Nodes,
+    Backtracks,
+    Cancelled,
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Time => "time limit",
+            Self::Nodes => "node limit",
+            Self::Backtracks => "backtrack limit",
+            Self::Cancelled => "cancellation",
+        })
+    }
+}
+
+/// Shared flag for stopping a search from another thread
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops every search given this token or a clone of it
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// When to give up on a search, with no limits by default. Nodes and backtracks are counted as
+/// in `SearchStats`
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimits {
+    pub time: Option<Duration>,
+    pub nodes: Option<u64>,
+    pub backtracks: Option<u64>,
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl SearchLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_time(mut self, time: Duration) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    pub fn with_nodes(mut self, nodes: u64) -> Self {
+        self.nodes = Some(nodes);
+        self
+    }
+
+    pub fn with_backtracks(mut self, backtracks: u64) -> Self {
+        self.backtracks = Some(backtracks);
+        self
+    }
+
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// The limit a search with these stats has reached, if any
+    pub fn reached(&self, stats: &SearchStats) -> Option<Limit> {
+        self.counts_reached(stats)
+            .or_else(|| self.clock_reached(stats.elapsed))
+    }
+
+    /// Node and backtrack limits, which are cheap enough to check every step
+    pub fn counts_reached(&self, stats: &SearchStats) -> Option<Limit> {
+        if self.nodes.is_some_and(|nodes| stats.nodes >= nodes) {
+            Some(Limit::Nodes)
+        } else if self
+            .backtracks
+            .is_some_and(|backtracks| stats.backtracks >= backtracks)
+        {
+            Some(Limit::Backtracks)
+        } else {
+            None
+        }
+    }
+
+    /// Time limit and cancellation
+    pub fn clock_reached(&self, elapsed: Duration) -> Option<Limit> {
+        if self
+            .cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+        {
+            Some(Limit::Cancelled)
+        } else if self.time.is_some_and(|time| elapsed >= time) {
+            Some(Limit::Time)
+        } else {
+            None
+        }
+    }
+}
+
+/// A search taken a step at a time which keeps `SearchStats`
+pub(crate) trait Stepwise {
+    type Error;
+
+    fn step(&mut self) -> Result<SearchStep, Self::Error>;
+
+    /// Counts so far, without the elapsed time
+    fn counts(&self) -> &SearchStats;
+
+    fn started(&self) -> Instant;
+}
+
+/// Steps until the search finds a schedule, finishes or reaches a limit
+pub(crate) fn find<T: Stepwise>(
+    search: &mut T,
+    limits: &SearchLimits,
+) -> Result<SearchOutcome, T::Error> {
+    let mut steps: u64 = 0;
+    loop {
+        match search.step()? {
+            SearchStep::Searching => {}
+            SearchStep::Found(schedule) => return Ok(SearchOutcome::Found(schedule)),
+            SearchStep::Finished if search.counts().solutions > 0 => {
+                return Ok(SearchOutcome::Exhausted)
+            }
+            SearchStep::Finished => return Ok(SearchOutcome::Infeasible),
+        }
+        steps += 1;
+        let stats = search.counts();
+        let mut limit = limits.counts_reached(stats);
+        if limit.is_none() && steps % CLOCK_INTERVAL == 0 {
+            limit = limits.clock_reached(search.started().elapsed());
+        }
+        if let Some(limit) = limit {
+            return Ok(SearchOutcome::LimitReached {
+                limit,
+                best_partial: stats.best_partial.clone(),
+            });
+        }
+    }
+}
@@ -1,240 +1,786 @@
-use bincode::Options;
+use std::error::Error;
+use std::ffi::OsString;
 use std::io::Read;
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
 
-use std::sync::Arc;
-fn step() {
-    let mut freelist = Vec::<Box<boardgame_scheduler::State>>::new();
-    let mut stack = vec![Box::new(boardgame_scheduler::State::new())];
+use boardgame_scheduler::{
+    count_solutions, Checkpoint, CheckpointError, Config, ConfigError, CsvExporter, CsvImporter,
+    DF2Solutions, Exporter, GridExporter, GridImporter, Importer, IsomorphismFilter, JsonExporter,
+    JsonImporter, MarkdownExporter, ParallelDF2, ParallelState, ParallelStats, PlayerSet, Roster,
+    Schedule, SearchLimits, SearchOutcome, SearchStats, SearchStep, SolutionCount, State,
+    StateSolutions, WidePlayerSet, DF2,
+};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
-    let mut max_get_players_played_count = 0;
-    let start = std::time::Instant::now();
-    let mut n = 1000;
-    let mut counter = 0;
-    loop {
-        let mut min_depth = stack.len();
-        let mut max_depth = stack.len();
-        let mut loop_start = std::time::Instant::now();
-        for _ in 0..n {
-            let mut state = stack.pop().unwrap();
-            let mut state2 = freelist
-                .pop()
-                .unwrap_or_else(|| Box::new(boardgame_scheduler::State::new()));
-
-            if let Ok(res) = state.step(&mut state2) {
-                let played = state.get_players_played_count();
-                if played > max_get_players_played_count {
-                    max_get_players_played_count = played;
-                }
+/// Steps between checks of the clock
+const CHECK_INTERVAL: u64 = 1 << 16;
 
-                let _ = res.unwrap();
-                stack.push(state);
-                stack.push(state2);
-            } else {
-                freelist.push(state);
-                freelist.push(state2);
+const EXIT_ERROR: u8 = 1;
+/// Also used when `validate` finds the schedule breaks its config
+const EXIT_INFEASIBLE: u8 = 2;
+const EXIT_LIMIT_REACHED: u8 = 3;
+
+#[derive(Parser)]
+#[command(
+    version,
+    about = "Seats players at tables over several rounds so that nobody meets twice",
+    after_help = "Exit codes: 0 when a schedule is found or valid, 1 on errors, 2 when no \
+                  schedule exists or the schedule is invalid, 3 when a limit is reached first"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Searches for a schedule
+    Solve {
+        #[command(flatten)]
+        config: ConfigArgs,
+        #[arg(long, value_enum, default_value_t = Solver::Df2)]
+        solver: Solver,
+        /// Give up after this many seconds
+        #[arg(long)]
+        time_limit: Option<f64>,
+        /// Give up after this many steps
+        #[arg(long)]
+        node_limit: Option<u64>,
+        /// Give up after backtracking this many times
+        #[arg(long)]
+        backtrack_limit: Option<u64>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Grid)]
+        format: OutputFormat,
+        /// Written to standard output if not given
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// File to save the search to every so often, which is resumed from if it exists. It is
+        /// removed once the search finishes, whether or not a schedule is found
+        #[arg(long)]
+        checkpoint: Option<PathBuf>,
+        /// Seconds between saving checkpoints
+        #[arg(long, default_value_t = 300.0, requires = "checkpoint")]
+        checkpoint_interval: f64,
+        /// Search on this many threads, which cannot be checkpointed
+        #[arg(long, conflicts_with = "checkpoint")]
+        threads: Option<usize>,
+    },
+    /// Checks a schedule against the config, printing every way it breaks it
+    ///
+    /// Without --rounds, --tables or --players-per-table the shape is taken from the schedule,
+    /// with as many tables as most rounds have and each table seating as many players as it
+    /// does in most rounds
+    Validate {
+        /// Schedule file, or - for standard input
+        input: PathBuf,
+        /// Guessed from the file extension if not given
+        #[arg(long, value_enum)]
+        from: Option<InputFormat>,
+        #[command(flatten)]
+        config: ConfigArgs,
+    },
+    /// Converts a schedule between formats
+    Render {
+        /// Schedule file, or - for standard input
+        input: PathBuf,
+        /// Guessed from the file extension if not given
+        #[arg(long, value_enum)]
+        from: Option<InputFormat>,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Grid)]
+        to: OutputFormat,
+        /// CSV file naming the players
+        #[arg(long)]
+        roster: Option<PathBuf>,
+        /// Written to standard output if not given
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Runs a search to the end, counting every schedule it finds
+    Count {
+        #[command(flatten)]
+        config: ConfigArgs,
+        #[arg(long, value_enum, default_value_t = Solver::Df2)]
+        solver: Solver,
+        /// Also count schedules that differ other than by reordering rounds, tables or players
+        #[arg(long)]
+        unique: bool,
+        /// Search on this many threads
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+}
+
+#[derive(Args)]
+struct ConfigArgs {
+    /// [default: 6]
+    #[arg(long)]
+    rounds: Option<usize>,
+    /// [default: 6]
+    #[arg(long)]
+    tables: Option<usize>,
+    /// [default: 4]
+    #[arg(long)]
+    players_per_table: Option<usize>,
+    /// Total number of players, when more players than seats means some sit out each round
+    #[arg(long)]
+    players: Option<usize>,
+    /// CSV file naming the players, which also sets the number of players
+    #[arg(long)]
+    roster: Option<PathBuf>,
+}
+
+impl ConfigArgs {
+    fn roster(&self) -> Result<Option<Roster>, Box<dyn Error>> {
+        self.roster.as_deref().map(read_roster).transpose()
+    }
+
+    /// Tables are taken from `shape` unless any of the table options are given, going by most
+    /// rounds so that a round with a table too many or a player missing is reported by
+    /// `Schedule::validate`
+    fn config(
+        &self,
+        roster: Option<&Roster>,
+        shape: Option<&Schedule>,
+    ) -> Result<Config, ConfigError> {
+        let mut config = match shape {
+            Some(schedule)
+                if self.rounds.is_none()
+                    && self.tables.is_none()
+                    && self.players_per_table.is_none() =>
+            {
+                let rounds = schedule.rounds();
+                let table_count = most_common(rounds.iter().map(Vec::len)).unwrap_or(0);
+                let table_sizes = (0..table_count)
+                    .map(|table| {
+                        let sizes = rounds.iter().filter_map(|tables| tables.get(table));
+                        most_common(sizes.map(Vec::len)).unwrap_or(0)
+                    })
+                    .collect();
+                let config = Config::with_table_sizes(rounds.len(), table_sizes)?;
+                let player_count = config.player_count().max(schedule.player_count());
+                config.with_player_count(player_count)?
             }
+            _ => Config::new(
+                self.rounds.unwrap_or(6),
+                self.tables.unwrap_or(6),
+                self.players_per_table.unwrap_or(4),
+            )?,
+        };
+        if let Some(roster) = roster {
+            config = config.with_roster(roster)?;
+        }
+        if let Some(players) = self.players {
+            config = config.with_player_count(players)?;
+        }
+        Ok(config)
+    }
+}
 
-            min_depth = min_depth.min(stack.len());
-            max_depth = max_depth.max(stack.len());
-        }
-
-        counter += n;
-        let last = &stack[min_depth - 1];
-        let last = &stack.last().unwrap();
-        println!("\n{:?}\n{}\n", last, last);
-        println!(
-            "Rate: {}, Stack size: {}, allocations: {}, min_depth: {}, max_depth: {}, max_get_players_played_count: {}",
-            counter as f32 / start.elapsed().as_secs_f32().max(0.1),
-            stack.len(),
-            stack.len() + freelist.len(),
-            min_depth,
-			max_depth,
-            max_get_players_played_count,
-        );
-        n = (((n as f64) / loop_start.elapsed().as_secs_f64().max(0.1)) as u64).max(1000) / 2;
+/// The value seen most often, preferring the larger of values seen equally often
+fn most_common<I: IntoIterator<Item = usize>>(values: I) -> Option<usize> {
+    let mut counts = std::collections::BTreeMap::new();
+    for value in values {
+        *counts.entry(value).or_insert(0) += 1;
     }
+    counts
+        .into_iter()
+        .max_by_key(|&(value, count)| (count, value))
+        .map(|(value, _)| value)
 }
 
-fn bstep() -> Result<(), Box<dyn std::error::Error>> {
-    let tree = sled::open("db")?;
+#[derive(Clone, Copy, ValueEnum)]
+enum Solver {
+    /// Depth first, one seat at a time
+    Df2,
+    /// Depth first with constraint propagation
+    State,
+}
 
-    let state = boardgame_scheduler::State::new();
-    let available_count = state.get_available_count() as usize;
+#[derive(Clone, Copy, ValueEnum)]
+enum InputFormat {
+    Grid,
+    Csv,
+    Json,
+}
 
-    let mut states = Arc::new(Vec::new());
-    Arc::make_mut(&mut states).push(Arc::new(Vec::new()));
-    Arc::make_mut(Arc::make_mut(&mut states).first_mut().unwrap()).push(state);
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    Grid,
+    Csv,
+    Json,
+    Markdown,
+}
 
-    loop {
-        let mut old_states = states.clone();
-
-        /*
-        Do stuff
-        */
-        let new_states = states.clone();
-        if let Some(old) = Arc::get_mut(&mut old_states) {
-            for (score, state_list) in old.iter_mut().enumerate() {
-                if let Some(state_list) = Arc::get_mut(state_list) {
-                    for state in state_list.iter() {
-                        //batch.remove()
-                    }
-                }
-            }
+impl InputFormat {
+    fn guess(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => Self::Csv,
+            Some("json") => Self::Json,
+            _ => Self::Grid,
         }
+    }
+
+    fn importer(self) -> Box<dyn Importer> {
+        match self {
+            Self::Grid => Box::new(GridImporter),
+            Self::Csv => Box::new(CsvImporter),
+            Self::Json => Box::new(JsonImporter),
+        }
+    }
+}
 
-        let mut batch = sled::Batch::default();
+impl OutputFormat {
+    fn exporter(self, roster: Option<Roster>) -> Box<dyn Exporter> {
+        match self {
+            Self::Grid => Box::new(GridExporter { roster }),
+            Self::Csv => Box::new(CsvExporter { roster }),
+            Self::Json => Box::new(JsonExporter {
+                pretty: true,
+                roster,
+            }),
+            Self::Markdown => Box::new(MarkdownExporter { roster }),
+        }
+    }
+}
 
-        for result in tree.iter().rev() {
-            let (val, _) = result?;
-            batch.remove(val);
+/// Calls `function::<S>(args)` with the smallest player set that holds `player_count` players
+macro_rules! with_player_set {
+    ($player_count:expr, $function:ident($($arg:expr),*)) => {
+        match $player_count {
+            0..=32 => $function::<u32>($($arg),*),
+            33..=64 => $function::<u64>($($arg),*),
+            65..=128 => $function::<u128>($($arg),*),
+            _ => $function::<WidePlayerSet<4>>($($arg),*),
         }
+    };
+}
+
+/// Either search, taken a step at a time so it can be stopped and saved between steps
+enum Search<S> {
+    Df2(DF2Solutions<S>),
+    State(StateSolutions<S>),
+}
 
-        tree.apply_batch(batch)?;
+impl<S: PlayerSet> Search<S> {
+    fn new(config: Config, solver: Solver) -> Result<Self, ConfigError> {
+        Ok(match solver {
+            Solver::Df2 => Self::Df2(DF2::new(config)?.solutions()),
+            Solver::State => Self::State(State::new(config)?.solutions()),
+        })
     }
 
-    let max_cache = 100_000;
-    let cache_diff_limit = 10;
+    fn load(path: &Path, solver: Solver) -> Result<Self, CheckpointError> {
+        Ok(match solver {
+            Solver::Df2 => Self::Df2(DF2Solutions::load_checkpoint(path)?),
+            Solver::State => Self::State(StateSolutions::load_checkpoint(path)?),
+        })
+    }
 
-    let state = boardgame_scheduler::State::new();
-    let available_count = state.get_available_count() as usize;
+    fn save(&self, path: &Path) -> Result<(), CheckpointError> {
+        match self {
+            Self::Df2(search) => search.save_checkpoint(path),
+            Self::State(search) => search.save_checkpoint(path),
+        }
+    }
 
-    let bincode_ops = bincode::DefaultOptions::new().with_fixint_encoding();
-    let serialized_size = bincode_ops.serialized_size(&state)?;
-    println!("Serialized size: {}", serialized_size);
+    fn config(&self) -> &Config {
+        match self {
+            Self::Df2(search) => search.config(),
+            Self::State(search) => search.config(),
+        }
+    }
 
-    let mut files = Vec::new();
-    for i in 0..available_count + 1 {
-        let file = std::fs::File::create(format!("tmp/tmp{}.txt", i))?;
-        let buf = std::io::BufWriter::new(file);
-        files.push((vec![], buf));
+    fn step(&mut self) -> SearchStep {
+        match self {
+            Self::Df2(search) => search.step(),
+            Self::State(search) => search.step(),
+        }
     }
-    files[available_count].0.push(state);
-    let start = std::time::Instant::now();
-    let mut n = 100000;
-    let mut counter = 0;
-    let mut i = 0;
-    let mut loop_start = std::time::Instant::now();
 
-    while let Some((mut stack, mut write_buf)) = files.pop() {
-        let current_count = files.len();
-        println!("Current_count: {}", current_count);
-        write_buf.flush()?;
-        let mut file_buf =
-            if let Ok(file) = std::fs::File::open(format!("tmp/tmp{}.txt", current_count)) {
-                let buf_read = std::io::BufReader::new(file);
-                Some(buf_read)
-            } else {
-                None
-            };
+    fn stats(&self) -> SearchStats {
+        match self {
+            Self::Df2(search) => search.stats(),
+            Self::State(search) => search.stats(),
+        }
+    }
 
-        let mut buf = vec![0; serialized_size as usize];
+    fn counts(&self) -> &SearchStats {
+        match self {
+            Self::Df2(search) => search.counts(),
+            Self::State(search) => search.counts(),
+        }
+    }
+}
 
-        let mut max_players_placed = 0;
-        let mut best = None;
+/// Where and how often `solve` saves its search
+struct Checkpointing {
+    path: PathBuf,
+    interval: Duration,
+}
 
-        'inner: loop {
-            let mut state = if let Some(state) = stack.pop() {
-                state
-            } else if file_buf
-                .as_mut()
-                .and_then(|file| file.read_exact(&mut buf).ok())
-                .is_some()
-            {
-                match bincode_ops.deserialize(&buf) {
-                    Ok(state) => state,
-                    Err(err) => {
-                        println!("Error decoding: {:?}", err);
-                        break 'inner;
-                    }
+/// Resumes from the checkpoint if there is one, and saves to it every so often and on running
+/// out of time. The checkpoint is removed once the search finishes, so the next run starts afresh
+fn solve<S: PlayerSet>(
+    config: Config,
+    solver: Solver,
+    limits: &SearchLimits,
+    checkpointing: Option<&Checkpointing>,
+) -> Result<SearchOutcome, Box<dyn Error>> {
+    let mut search = match checkpointing {
+        Some(checkpointing) if checkpointing.path.exists() => {
+            let search = Search::<S>::load(&checkpointing.path, solver)?;
+            if *search.config() != config {
+                return Err("The checkpoint is of a search with a different config".into());
+            }
+            log::info!("Resuming from {}", checkpointing.path.display());
+            search
+        }
+        _ => Search::new(config, solver)?,
+    };
+    let start = Instant::now();
+    let mut last_report = start;
+    let mut last_save = start;
+    let mut steps: u64 = 0;
+    loop {
+        // Limits are checked before each step, so a limit of zero stops the search at once
+        let on_interval = steps % CHECK_INTERVAL == 0;
+        let mut limit = limits.counts_reached(search.counts());
+        if limit.is_none() && on_interval {
+            limit = limits.clock_reached(start.elapsed());
+        }
+        if limit.is_some() || on_interval {
+            let now = Instant::now();
+            let stats = search.stats();
+            if now - last_report >= Duration::from_secs(1) {
+                last_report = now;
+                log::info!("{}", stats);
+            }
+            if let Some(checkpointing) = checkpointing {
+                if limit.is_some() || now - last_save >= checkpointing.interval {
+                    last_save = now;
+                    search.save(&checkpointing.path)?;
+                    log::info!("Saved checkpoint to {}", checkpointing.path.display());
                 }
-            } else {
-                let _ = std::fs::remove_file(format!("tmp{}.txt", current_count));
-                break 'inner;
-            };
-            counter += 1;
-            let players_placed = state.get_players_played_count();
-            if players_placed > max_players_placed {
-                max_players_placed = players_placed;
-                best = Some(state);
             }
+            if let Some(limit) = limit {
+                log::info!("{}", stats);
+                return Ok(SearchOutcome::LimitReached {
+                    limit,
+                    best_partial: stats.best_partial,
+                });
+            }
+        }
+        let outcome = match search.step() {
+            SearchStep::Searching => None,
+            SearchStep::Found(schedule) => Some(SearchOutcome::Found(schedule)),
+            SearchStep::Finished => Some(SearchOutcome::Infeasible),
+        };
+        if let Some(outcome) = outcome {
+            log::info!("{}", search.stats());
+            if let Some(checkpointing) = checkpointing.filter(|c| c.path.exists()) {
+                std::fs::remove_file(&checkpointing.path)?;
+                log::info!("Removed checkpoint {}", checkpointing.path.display());
+            }
+            return Ok(outcome);
+        }
+        steps += 1;
+    }
+}
 
-            let mut callback = |state: &boardgame_scheduler::State| {
-                //if state.find_hidden_singles().is_err() {
-                //    return;
-                //}
-                let available_count = state.get_available_count() as usize;
-                assert!(available_count <= current_count);
-                if available_count == current_count {
-                    stack.push(*state);
-                } else {
-                    let target = &mut files[available_count];
-                    if (available_count + cache_diff_limit < current_count)
-                        || target.0.len() > max_cache
-                    {
-                        bincode_ops.serialize_into(&mut target.1, state).unwrap();
-                    } else {
-                        target.0.push(*state);
-                    }
-                }
-                //let score = 24 * 6 * 6 - score;
-            };
-            state.bstep(&mut callback);
-
-            //println!("Allocatins: {}", allocations);
-
-            i += 1;
-            if i > n {
-                println!(
-                    "Rate: {}, current available_count: {}",
-                    counter as f32 / start.elapsed().as_secs_f32().max(0.1),
-                    current_count
-                );
-
-                n = (((n as f64) / loop_start.elapsed().as_secs_f64().max(0.1)) as u64).max(1000)
-                    / 2;
-                loop_start = std::time::Instant::now();
-                i = 0;
+fn solve_parallel<S: PlayerSet>(
+    config: Config,
+    solver: Solver,
+    limits: &SearchLimits,
+    threads: usize,
+) -> Result<SearchOutcome, ConfigError> {
+    let (outcome, stats) = match solver {
+        Solver::Df2 => {
+            let search = ParallelDF2::<S>::new(config)?
+                .with_threads(threads)
+                .with_limits(limits.clone());
+            let stats = search.stats();
+            (reporting(&stats, || search.find()), stats)
+        }
+        Solver::State => {
+            let search = ParallelState::<S>::new(config)?
+                .with_threads(threads)
+                .with_limits(limits.clone());
+            let stats = search.stats();
+            (reporting(&stats, || search.find()), stats)
+        }
+    };
+    log::info!("{}", stats.snapshot());
+    Ok(outcome)
+}
+
+/// Runs a search on other threads, logging its stats every second as `solve` does
+fn reporting<T>(stats: &ParallelStats, search: impl FnOnce() -> T) -> T {
+    let (finished, finishing) = mpsc::channel::<()>();
+    std::thread::scope(|scope| {
+        scope.spawn(move || {
+            while finishing.recv_timeout(Duration::from_secs(1)) == Err(RecvTimeoutError::Timeout) {
+                log::info!("{}", stats.snapshot());
             }
+        });
+        let result = search();
+        drop(finished);
+        result
+    })
+}
+
+fn count<S: PlayerSet>(
+    config: Config,
+    solver: Solver,
+    unique: bool,
+    threads: Option<usize>,
+) -> Result<SolutionCount, ConfigError> {
+    let filter = if unique {
+        Some(IsomorphismFilter::new(&config)?)
+    } else {
+        None
+    };
+    Ok(match (solver, threads) {
+        (Solver::Df2, Some(threads)) => {
+            let search = ParallelDF2::<S>::new(config)?.with_threads(threads);
+            reporting(&search.stats(), || search.count(filter))
         }
-        if let Some(best) = best {
-            println!("Max players placed: {}", max_players_placed);
-            println!("State: {:}", best);
+        (Solver::Df2, None) => count_solutions(DF2::<S>::new(config)?.solutions(), filter),
+        (Solver::State, Some(threads)) => {
+            let search = ParallelState::<S>::new(config)?.with_threads(threads);
+            reporting(&search.stats(), || search.count(filter))
         }
+        (Solver::State, None) => count_solutions(State::<S>::new(config)?.solutions(), filter),
+    })
+}
+
+/// The value of a command line option given in seconds, which must be finite and not negative
+fn seconds(option: &str, seconds: f64) -> Result<Duration, Box<dyn Error>> {
+    Duration::try_from_secs_f64(seconds).map_err(|_| {
+        format!(
+            "--{} must be a finite number of seconds, not {}",
+            option, seconds
+        )
+        .into()
+    })
+}
+
+fn read_input(path: &Path) -> std::io::Result<String> {
+    if path == Path::new("-") {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input)?;
+        Ok(input)
+    } else {
+        std::fs::read_to_string(path)
     }
-    Ok(())
 }
 
-fn df2() {
-    let mut state = boardgame_scheduler::DF2::new();
+fn read_roster(path: &Path) -> Result<Roster, Box<dyn Error>> {
+    Ok(Roster::from_csv(&read_input(path)?)?)
+}
 
-    let start = std::time::Instant::now();
-    let mut n = 2;
-    let mut counter = 0;
-    let mut max_players_placed = 0;
-    loop {
-        let loop_start = std::time::Instant::now();
-        for _ in 0..n {
-            log::trace!("State: {:?}", state);
-            state.step().unwrap();
-            if state.get_players_placed() > max_players_placed {
-                max_players_placed = state.get_players_placed();
+fn read_schedule(path: &Path, format: Option<InputFormat>) -> Result<Schedule, Box<dyn Error>> {
+    let format = format.unwrap_or_else(|| InputFormat::guess(path));
+    Ok(format.importer().import(&read_input(path)?)?)
+}
+
+/// Ends the output with a newline, which the grid leaves off
+fn write_schedule(
+    schedule: &Schedule,
+    exporter: &dyn Exporter,
+    output: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let mut text = exporter.export_string(schedule)?;
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    match output {
+        Some(path) => std::fs::write(path, text)?,
+        None => std::io::stdout().lock().write_all(text.as_bytes())?,
+    }
+    Ok(())
+}
+
+fn run(command: Command) -> Result<u8, Box<dyn Error>> {
+    match command {
+        Command::Solve {
+            config,
+            solver,
+            time_limit,
+            node_limit,
+            backtrack_limit,
+            format,
+            output,
+            checkpoint,
+            checkpoint_interval,
+            threads,
+        } => {
+            let roster = config.roster()?;
+            let config = config.config(roster.as_ref(), None)?;
+            let limits = SearchLimits {
+                time: time_limit
+                    .map(|time_limit| seconds("time-limit", time_limit))
+                    .transpose()?,
+                nodes: node_limit,
+                backtracks: backtrack_limit,
+                cancellation: None,
+            };
+            let interval = seconds("checkpoint-interval", checkpoint_interval)?;
+            let checkpointing = checkpoint.map(|path| Checkpointing { path, interval });
+            let start = Instant::now();
+            let outcome = match threads {
+                Some(threads) => with_player_set!(
+                    config.player_count(),
+                    solve_parallel(config, solver, &limits, threads)
+                )?,
+                None => with_player_set!(
+                    config.player_count(),
+                    solve(config, solver, &limits, checkpointing.as_ref())
+                )?,
+            };
+            log::info!("Searched for {:.1}s", start.elapsed().as_secs_f64());
+            match outcome {
+                SearchOutcome::Found(schedule) => {
+                    write_schedule(&schedule, &*format.exporter(roster), output.as_deref())?;
+                    Ok(0)
+                }
+                SearchOutcome::Infeasible => {
+                    eprintln!("No schedule exists");
+                    Ok(EXIT_INFEASIBLE)
+                }
+                SearchOutcome::Exhausted => {
+                    eprintln!("No further schedule exists");
+                    Ok(EXIT_INFEASIBLE)
+                }
+                SearchOutcome::LimitReached {
+                    limit,
+                    best_partial,
+                } => {
+                    eprintln!("Stopped by the {} before finding a schedule", limit);
+                    if let Some(partial) = best_partial {
+                        let placed: usize = partial.rounds().iter().flatten().map(Vec::len).sum();
+                        let grid = GridExporter { roster }.export_string(&partial)?;
+                        eprintln!("Most players placed: {}\n{}", placed, grid);
+                    }
+                    Ok(EXIT_LIMIT_REACHED)
+                }
             }
         }
-        counter += n;
-        log::info!(
-            "Rate: {}, max_players_placed: {}",
-            counter as f32 / start.elapsed().as_secs_f32().max(0.1),
-            max_players_placed
-        );
-        n = (((n as f64) / loop_start.elapsed().as_secs_f64().max(0.1)) as u64).max(1000) / 2;
+        Command::Validate {
+            input,
+            from,
+            config,
+        } => {
+            let schedule = read_schedule(&input, from)?;
+            let roster = config.roster()?;
+            let config = config.config(roster.as_ref(), Some(&schedule))?;
+            let violations = schedule.validate(&config);
+            for violation in violations.iter() {
+                println!("{}", violation);
+            }
+            if violations.is_empty() {
+                println!("Valid");
+                Ok(0)
+            } else {
+                Ok(EXIT_INFEASIBLE)
+            }
+        }
+        Command::Render {
+            input,
+            from,
+            to,
+            roster,
+            output,
+        } => {
+            let schedule = read_schedule(&input, from)?;
+            let roster = roster.as_deref().map(read_roster).transpose()?;
+            write_schedule(&schedule, &*to.exporter(roster), output.as_deref())?;
+            Ok(0)
+        }
+        Command::Count {
+            config,
+            solver,
+            unique,
+            threads,
+        } => {
+            let roster = config.roster()?;
+            let config = config.config(roster.as_ref(), None)?;
+            let count = with_player_set!(
+                config.player_count(),
+                count(config, solver, unique, threads)
+            )?;
+            println!("Solutions: {}", count.solutions);
+            if let Some(unique) = count.unique {
+                println!("Unique: {}", unique);
+            }
+            println!("Took: {:.1}s", count.elapsed.as_secs_f64());
+            Ok(if count.solutions == 0 {
+                EXIT_INFEASIBLE
+            } else {
+                0
+            })
+        }
+    }
+}
+
+/// Runs the command line, reporting bad arguments like any other error rather than with clap's
+/// usual exit code, which would be taken for an infeasible config
+fn exit_code<I, T>(args: I) -> u8
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let cli = match Cli::try_parse_from(args) {
+        Ok(cli) => cli,
+        Err(error) => {
+            let _ = error.print();
+            return if error.use_stderr() { EXIT_ERROR } else { 0 };
+        }
+    };
+    match run(cli.command) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            EXIT_ERROR
+        }
     }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> ExitCode {
     let mut builder = env_logger::Builder::from_default_env();
     builder.filter_level(log::LevelFilter::Info);
     builder.init();
-    df2();
-    Ok(())
+    ExitCode::from(exit_code(std::env::args_os()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use boardgame_scheduler::Violation;
+
+    fn inferred_config(schedule: &Schedule) -> Config {
+        let args = ConfigArgs {
+            rounds: None,
+            tables: None,
+            players_per_table: None,
+            players: None,
+            roster: None,
+        };
+        args.config(None, Some(schedule)).unwrap()
+    }
+
+    /// Runs the command line with the given arguments after the program name
+    fn exit_code_of(args: &[&str]) -> u8 {
+        exit_code(std::iter::once("boardgame_scheduler").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn exit_codes() {
+        let directory = std::env::temp_dir().join(format!("cli_test_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let found = directory.join("found.txt");
+        let found_arg = found.to_str().unwrap();
+        let small = ["--rounds", "3", "--tables", "4", "--players-per-table", "2"];
+
+        let solve = [&["solve", "--output", found_arg][..], &small].concat();
+        assert_eq!(exit_code_of(&solve), 0);
+        assert_eq!(exit_code_of(&["validate", found_arg]), 0);
+        let validate = [&["validate", found_arg][..], &small].concat();
+        assert_eq!(exit_code_of(&validate), 0);
+        let count = [&["count", "--solver", "state"][..], &small].concat();
+        assert_eq!(exit_code_of(&count), 0);
+
+        // Four players can only meet in three rounds of pairs
+        let infeasible = ["--rounds", "4", "--tables", "2", "--players-per-table", "2"];
+        assert_eq!(
+            exit_code_of(&[&["solve"][..], &infeasible].concat()),
+            EXIT_INFEASIBLE
+        );
+        assert_eq!(
+            exit_code_of(&[&["count"][..], &infeasible].concat()),
+            EXIT_INFEASIBLE
+        );
+        let limited = ["solve", "--node-limit", "100"];
+        assert_eq!(exit_code_of(&limited), EXIT_LIMIT_REACHED);
+        // The small config takes 44 steps, far fewer than are taken between clock checks
+        let few_nodes = [&["solve", "--node-limit", "5"][..], &small].concat();
+        assert_eq!(exit_code_of(&few_nodes), EXIT_LIMIT_REACHED);
+        let no_time = [&["solve", "--time-limit", "0"][..], &small].concat();
+        assert_eq!(exit_code_of(&no_time), EXIT_LIMIT_REACHED);
+
+        // Checkpoints are kept for a search stopped by a limit, and removed once one finishes
+        let checkpoint = directory.join("search.checkpoint");
+        let checkpoint_arg = checkpoint.to_str().unwrap();
+        let limited = [&limited[..], &["--checkpoint", checkpoint_arg]].concat();
+        assert_eq!(exit_code_of(&limited), EXIT_LIMIT_REACHED);
+        assert!(checkpoint.exists());
+        let small_config = Config::new(3, 4, 2).unwrap();
+        let search = DF2::<u32>::new(small_config).unwrap().solutions();
+        search.save_checkpoint(&checkpoint).unwrap();
+        let solve = [&solve[..], &["--checkpoint", checkpoint_arg]].concat();
+        assert_eq!(exit_code_of(&solve), 0);
+        assert!(!checkpoint.exists());
+
+        assert_eq!(exit_code_of(&["solve", "--time-limit=-1"]), EXIT_ERROR);
+        assert_eq!(exit_code_of(&["solve", "--time-limit", "NaN"]), EXIT_ERROR);
+        assert_eq!(exit_code_of(&["solve", "--no-such-option"]), EXIT_ERROR);
+        let missing = directory.join("missing.txt");
+        assert_eq!(
+            exit_code_of(&["validate", missing.to_str().unwrap()]),
+            EXIT_ERROR
+        );
+        assert_eq!(exit_code_of(&["--help"]), 0);
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn validate_infers_shape() {
+        let directory = std::env::temp_dir().join(format!("cli_shape_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        // Player 3 is missing from the second round
+        let schedule = Schedule::new(vec![
+            vec![vec![0, 1], vec![2, 3], vec![4, 5]],
+            vec![vec![2, 4], vec![0, 5], vec![1]],
+        ]);
+        let path = directory.join("schedule.json");
+        let json = JsonExporter::default().export_string(&schedule).unwrap();
+        std::fs::write(&path, json).unwrap();
+        let config = inferred_config(&schedule);
+        assert_eq!(config, Config::new(2, 3, 2).unwrap());
+        let violations = schedule.validate(&config);
+        assert!(matches!(
+            violations[..],
+            [
+                Violation::WrongTableSize {
+                    round: 1,
+                    table: 2,
+                    seats: 1,
+                    expected: 2,
+                },
+                Violation::MissingPlayer {
+                    player: 3,
+                    round: 1
+                }
+            ]
+        ));
+        let path = path.to_str().unwrap();
+        assert_eq!(exit_code_of(&["validate", path]), EXIT_INFEASIBLE);
+
+        // A table too many in one round
+        let schedule = Schedule::new(vec![
+            vec![vec![0, 1], vec![2, 3], vec![4, 5]],
+            vec![vec![2, 4], vec![0, 5], vec![1, 3], vec![6, 7]],
+            vec![vec![3, 5], vec![1, 4], vec![0, 2]],
+        ]);
+        let violations = schedule.validate(&inferred_config(&schedule));
+        assert!(violations
+            .iter()
+            .any(|violation| matches!(violation, Violation::WrongTableCount { round: 1, .. })));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
 }
@@ -0,0 +1,144 @@
+//! Who met whom in a schedule, for judging how well it mixes players
+
+use serde::{Deserialize, Serialize};
+
+use crate::export::label;
+use crate::{Roster, Schedule};
+
+/// Meetings between every pair of players, where a meeting is sharing a table in a round
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct MeetingReport {
+    /// Times each pair met, indexed by player then player, with 0 for a player and themselves
+    pub counts: Vec<Vec<u32>>,
+    /// Pairs that never met, lowest player first
+    pub never_met: Vec<(usize, usize)>,
+    /// Pairs that met more than once with the number of times they met, lowest player first
+    pub met_repeatedly: Vec<(usize, usize, u32)>,
+    /// Number of different players each player met
+    pub distinct_opponents: Vec<usize>,
+}
+
+impl Schedule {
+    /// Meetings so far, which also works on partly filled schedules. Players the schedule does
+    /// not seat are included up to `player_count`
+    pub fn meeting_report(&self, player_count: usize) -> MeetingReport {
+        let player_count = player_count.max(self.player_count());
+        let mut counts = vec![vec![0; player_count]; player_count];
+        for players in self.rounds().iter().flatten() {
+            // A player listed twice at a table still only meets each tablemate once
+            let mut players = players.clone();
+            players.sort_unstable();
+            players.dedup();
+            for &first in players.iter() {
+                for &second in players.iter().filter(|&&second| second != first) {
+                    counts[first as usize][second as usize] += 1;
+                }
+            }
+        }
+        let mut never_met = Vec::new();
+        let mut met_repeatedly = Vec::new();
+        for (first, row) in counts.iter().enumerate() {
+            for (second, &count) in row.iter().enumerate().skip(first + 1) {
+                match count {
+                    0 => never_met.push((first, second)),
+                    1 => {}
+                    count => met_repeatedly.push((first, second, count)),
+                }
+            }
+        }
+        let distinct_opponents = counts
+            .iter()
+            .map(|row| row.iter().filter(|&&count| count != 0).count())
+            .collect();
+        MeetingReport {
+            counts,
+            never_met,
+            met_repeatedly,
+            distinct_opponents,
+        }
+    }
+}
+
+impl MeetingReport {
+    pub fn player_count(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Fraction of pairs that have met at least once
+    pub fn coverage(&self) -> f64 {
+        let player_count = self.player_count();
+        let pair_count = player_count * player_count.saturating_sub(1) / 2;
+        if pair_count == 0 {
+            return 1.0;
+        }
+        1.0 - self.never_met.len() as f64 / pair_count as f64
+    }
+
+    /// Writes the matrix, with players numbered, followed by the lists, with players named from
+    /// the roster if there is one
+    pub fn format<W: core::fmt::Write>(
+        &self,
+        roster: Option<&Roster>,
+        output: &mut W,
+    ) -> core::fmt::Result {
+        let width = self
+            .player_count()
+            .saturating_sub(1)
+            .to_string()
+            .len()
+            .max(2);
+        write!(output, "{:>width$} ", "", width = width)?;
+        for player in 0..self.player_count() {
+            write!(output, " {:>width$}", player, width = width)?;
+        }
+        writeln!(output)?;
+        for (first, row) in self.counts.iter().enumerate() {
+            write!(output, "{:>width$}:", first, width = width)?;
+            for (second, &count) in row.iter().enumerate() {
+                let cell = match count {
+                    _ if first == second => "-".to_string(),
+                    0 => ".".to_string(),
+                    count => count.to_string(),
+                };
+                write!(output, " {:>width$}", cell, width = width)?;
+            }
+            writeln!(output)?;
+        }
+
+        writeln!(output, "\nPairs met: {:.1}%", self.coverage() * 100.0)?;
+        let pair = |first: usize, second: usize| {
+            format!("{} & {}", label(roster, first), label(roster, second))
+        };
+        let never_met: Vec<String> = self
+            .never_met
+            .iter()
+            .map(|&(first, second)| pair(first, second))
+            .collect();
+        writeln!(output, "Never met: {}", list(&never_met))?;
+        let met_repeatedly: Vec<String> = self
+            .met_repeatedly
+            .iter()
+            .map(|&(first, second, count)| format!("{} ({} times)", pair(first, second), count))
+            .collect();
+        writeln!(output, "Met more than once: {}", list(&met_repeatedly))?;
+        writeln!(output, "Distinct opponents:")?;
+        for (player, count) in self.distinct_opponents.iter().enumerate() {
+            writeln!(output, "  {}: {}", label(roster, player), count)?;
+        }
+        Ok(())
+    }
+}
+
+fn list(items: &[String]) -> String {
+    if items.is_empty() {
+        "none".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+impl std::fmt::Display for MeetingReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.format(None, f)
+    }
+}
@@ -0,0 +1,81 @@
+//! Hooks for watching a search as it runs, for logging, drawing or tracing it
+//!
+//! Events of the depth first searches follow the partial schedule the search is working on, so
+//! replaying the placements and removals on top of the one the search was made from rebuilds it at
+//! any point.
+
+use crate::{Round, Schedule, Table};
+
+/// Callbacks made by the searches. Every callback does nothing by default, so observers only
+/// implement the events they care about
+///
+/// The depth first searches, `DF2` and its `DF2Solutions` and the `StateSolutions` driver of
+/// `State`, call `dead_end` or `solution_found` for every partial schedule they cannot extend,
+/// then `backtracked` once for going back from it, unless there is nowhere left to go. `DF2` goes
+/// back a seat at a time, while a `State` search goes back to the latest state with candidates
+/// left, which can be several players earlier.
+///
+/// `FrontierSearch` and `ParallelState` have no single partial schedule to follow, as they move
+/// between many states in turn. They report the players each new state adds to the one it was
+/// made from, and `dead_end` and `solution_found`, but never `removed` or `backtracked`.
+/// `ParallelDF2` gives each thread its own observer, which follows the thread's search through
+/// each subtree in turn, starting from the prefix of the subtree.
+pub trait SearchObserver {
+    /// `player` was seated at `table` in `round`
+    fn placed(&mut self, _round: Round, _table: Table, _player: usize) {}
+
+    /// `player` was taken off `table` in `round`
+    fn removed(&mut self, _round: Round, _table: Table, _player: usize) {}
+
+    /// The search went back from a dead end or solution to an earlier partial schedule, now with
+    /// `players_placed` players, to try the next candidate there. Called after the removals
+    fn backtracked(&mut self, _players_placed: usize) {}
+
+    /// The partial schedule with `players_placed` players has no candidates left to try
+    fn dead_end(&mut self, _players_placed: usize) {}
+
+    fn solution_found(&mut self, _schedule: &Schedule) {}
+
+    /// Whether to report placements and removals, which `State` searches work out by comparing
+    /// partial schedules
+    fn watches_players(&self) -> bool {
+        true
+    }
+}
+
+/// Observer which ignores every event, used by the plain `step` methods
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoObserver;
+
+impl SearchObserver for NoObserver {
+    #[inline]
+    fn watches_players(&self) -> bool {
+        false
+    }
+}
+
+impl<O: SearchObserver + ?Sized> SearchObserver for &mut O {
+    fn placed(&mut self, round: Round, table: Table, player: usize) {
+        (**self).placed(round, table, player)
+    }
+
+    fn removed(&mut self, round: Round, table: Table, player: usize) {
+        (**self).removed(round, table, player)
+    }
+
+    fn backtracked(&mut self, players_placed: usize) {
+        (**self).backtracked(players_placed)
+    }
+
+    fn dead_end(&mut self, players_placed: usize) {
+        (**self).dead_end(players_placed)
+    }
+
+    fn solution_found(&mut self, schedule: &Schedule) {
+        (**self).solution_found(schedule)
+    }
+
+    fn watches_players(&self) -> bool {
+        (**self).watches_players()
+    }
+}
@@ -0,0 +1,581 @@
+//! Running the searches on several threads
+//!
+//! `ParallelDF2` splits the search into subtrees up front. Every schedule the search reaches
+//! starts with one of the prefixes it enumerates, so each prefix is searched on its own with
+//! `DF2::from_slice` and `DF2::subtree`. Each thread starts with its share of the prefixes, taking
+//! them in search order, and once out of work takes the last prefixes from another thread.
+//!
+//! `ParallelState` shares the work as it goes instead. Each thread explores depth first from its
+//! own stack of states, and a thread whose stack is empty takes the shallowest state from another
+//! thread, which has the most left below it. Threads with nothing to take sleep until another
+//! thread adds states or the search ends.
+
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::{
+    Config, ConfigError, DF2Solutions, IsomorphismFilter, Limit, NoObserver, PlayerSet, Schedule,
+    SearchLimits, SearchObserver, SearchOutcome, SearchStats, SearchStep, SolutionCount, State,
+    DF2,
+};
+
+/// Prefixes enumerated for each thread, so that threads which finish early can take more work
+pub const SUBTREES_PER_THREAD: usize = 16;
+
+/// Steps between updates of `ParallelStats`, when the limits are checked
+const CHECK_INTERVAL: u64 = 1 << 12;
+
+/// Tells every thread to stop, recording which limit stopped them if any
+#[derive(Default)]
+struct Stop {
+    stopped: AtomicBool,
+    limit: Mutex<Option<Limit>>,
+}
+
+impl Stop {
+    fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+
+    fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    fn reach(&self, limit: Limit) {
+        self.limit.lock().unwrap().get_or_insert(limit);
+        self.stop();
+    }
+
+    /// Found if there is a schedule, otherwise whether the search ended or was stopped
+    fn outcome(self, found: Option<Schedule>, stats: &ParallelStats) -> SearchOutcome {
+        match (found, self.limit.into_inner().unwrap()) {
+            (Some(schedule), _) => SearchOutcome::Found(schedule),
+            (None, Some(limit)) => SearchOutcome::LimitReached {
+                limit,
+                best_partial: stats.snapshot().best_partial,
+            },
+            (None, None) => SearchOutcome::Infeasible,
+        }
+    }
+}
+
+/// Searches with `DF2` on several threads, either for the first schedule any thread finds or
+/// for every schedule
+#[derive(Clone, Debug)]
+pub struct ParallelDF2<S = u32> {
+    config: Config,
+    threads: usize,
+    limits: SearchLimits,
+    stats: Arc<ParallelStats>,
+    player_set: PhantomData<S>,
+}
+
+impl<S: PlayerSet> ParallelDF2<S> {
+    /// Uses as many threads as the machine has cores
+    pub fn new(config: Config) -> Result<Self, ConfigError> {
+        let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+        config.check_capacity::<S>()?;
+        Ok(Self {
+            config,
+            threads,
+            limits: SearchLimits::default(),
+            stats: Arc::default(),
+            player_set: PhantomData,
+        })
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Stops `find` once any is reached, checking every few thousand steps of each thread
+    pub fn with_limits(mut self, limits: SearchLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Shared with the threads, so progress can be followed from elsewhere
+    pub fn stats(&self) -> Arc<ParallelStats> {
+        Arc::clone(&self.stats)
+    }
+
+    /// Players to place after those `DF2::new` places, one list for each subtree, in search
+    /// order. Prefixes are extended a seat at a time until there are enough of them, and those
+    /// that cannot be extended are kept as they are
+    pub fn prefixes(&self) -> Vec<Vec<u8>> {
+        let target = self.threads * SUBTREES_PER_THREAD;
+        let mut prefixes = vec![Vec::new()];
+        while prefixes.len() < target {
+            let mut extended = false;
+            let mut next = Vec::new();
+            for prefix in prefixes {
+                let children: Vec<Vec<u8>> = (0..self.config.player_count() as u8)
+                    .map(|player| {
+                        let mut child = prefix.clone();
+                        child.push(player);
+                        child
+                    })
+                    .filter(|child| DF2::<S>::from_slice(self.config.clone(), child).is_ok())
+                    .collect();
+                if children.is_empty() {
+                    next.push(prefix);
+                } else {
+                    extended = true;
+                    next.extend(children);
+                }
+            }
+            prefixes = next;
+            if !extended {
+                break;
+            }
+        }
+        prefixes
+    }
+
+    /// The first schedule any thread finds, which need not be the first in search order
+    pub fn find(&self) -> SearchOutcome {
+        self.find_observed(&NoObserver)
+    }
+
+    /// Like `find`, giving each thread its own clone of `observer` to tell what it does
+    pub fn find_observed<O: SearchObserver + Send + Clone>(&self, observer: &O) -> SearchOutcome {
+        let found = Mutex::new(None);
+        let stop = self.run(observer, |search, stop, observer| {
+            let mut steps: u64 = 0;
+            loop {
+                match search.step_observed(observer) {
+                    SearchStep::Searching => {}
+                    SearchStep::Found(schedule) => {
+                        found.lock().unwrap().get_or_insert(schedule);
+                        stop.stop();
+                        return;
+                    }
+                    SearchStep::Finished => return,
+                }
+                steps += 1;
+                if steps % CHECK_INTERVAL != 0 {
+                    continue;
+                }
+                self.stats.add(search.take_stats());
+                if let Some(limit) = self.stats.reached(&self.limits) {
+                    stop.reach(limit);
+                }
+                if stop.is_stopped() {
+                    return;
+                }
+            }
+        });
+        stop.outcome(found.into_inner().unwrap(), &self.stats)
+    }
+
+    /// Counts every schedule, like `count_solutions` on `DF2::solutions`. The limits are not
+    /// applied
+    pub fn count(&self, filter: Option<IsomorphismFilter>) -> SolutionCount {
+        self.count_observed(filter, &NoObserver)
+    }
+
+    /// Like `count`, giving each thread its own clone of `observer` to tell what it does
+    pub fn count_observed<O: SearchObserver + Send + Clone>(
+        &self,
+        filter: Option<IsomorphismFilter>,
+        observer: &O,
+    ) -> SolutionCount {
+        let start = Instant::now();
+        let totals = Mutex::new((0, 0));
+        let filter = filter.map(Mutex::new);
+        self.run(observer, |search, _, observer| {
+            let mut count = 0;
+            let mut unique = 0;
+            loop {
+                let schedule = match search.step_observed(observer) {
+                    SearchStep::Searching => continue,
+                    SearchStep::Found(schedule) => schedule,
+                    SearchStep::Finished => break,
+                };
+                count += 1;
+                if let Some(filter) = filter.as_ref() {
+                    if filter.lock().unwrap().insert_schedule(schedule.rounds()) {
+                        unique += 1;
+                    }
+                }
+            }
+            let mut totals = totals.lock().unwrap();
+            totals.0 += count;
+            totals.1 += unique;
+        });
+        let (solutions, unique) = totals.into_inner().unwrap();
+        SolutionCount {
+            solutions,
+            unique: filter.map(|_| unique),
+            elapsed: start.elapsed(),
+        }
+    }
+
+    /// Hands out the subtrees to the threads and calls `search` on each until the work runs out
+    /// or `search` stops the threads. Each thread passes its own clone of `observer` to `search`
+    fn run<O, F>(&self, observer: &O, search: F) -> Stop
+    where
+        O: SearchObserver + Send + Clone,
+        F: Fn(&mut DF2Solutions<S>, &Stop, &mut O) + Sync,
+    {
+        let queues: Vec<Mutex<VecDeque<Vec<u8>>>> = (0..self.threads)
+            .map(|_| Mutex::new(VecDeque::new()))
+            .collect();
+        for (index, prefix) in self.prefixes().into_iter().enumerate() {
+            queues[index % self.threads]
+                .lock()
+                .unwrap()
+                .push_back(prefix);
+        }
+        let stop = Stop::default();
+        let started = self.stats.restart();
+        std::thread::scope(|scope| {
+            for thread in 0..self.threads {
+                let (queues, stop, search) = (&queues, &stop, &search);
+                let mut observer = observer.clone();
+                scope.spawn(move || {
+                    let mut subtrees = 0;
+                    while !stop.is_stopped() {
+                        let prefix = match take(queues, thread) {
+                            Some(prefix) => prefix,
+                            None => break,
+                        };
+                        let mut solutions = DF2::<S>::from_slice(self.config.clone(), &prefix)
+                            .expect("prefixes are checked when enumerated")
+                            .subtree()
+                            .solutions();
+                        search(&mut solutions, stop, &mut observer);
+                        self.stats.add(solutions.take_stats());
+                        subtrees += 1;
+                    }
+                    log::debug!(
+                        "Thread {} searched {} subtrees in {:?}",
+                        thread,
+                        subtrees,
+                        started.elapsed()
+                    );
+                });
+            }
+        });
+        stop
+    }
+}
+
+/// Takes from the front of the thread's own queue, or failing that from the back of another's
+fn take(queues: &[Mutex<VecDeque<Vec<u8>>>], thread: usize) -> Option<Vec<u8>> {
+    if let Some(prefix) = queues[thread].lock().unwrap().pop_front() {
+        return Some(prefix);
+    }
+    (1..queues.len())
+        .map(|offset| (thread + offset) % queues.len())
+        .find_map(|victim| queues[victim].lock().unwrap().pop_back())
+}
+
+/// Progress of the latest parallel search, which threads add to as they run. It can be read
+/// from another thread while the search runs
+#[derive(Debug, Default)]
+pub struct ParallelStats {
+    stats: Mutex<SearchStats>,
+    started: Mutex<Option<Instant>>,
+}
+
+impl ParallelStats {
+    /// Totals so far, which lag each running thread by a few thousand steps
+    pub fn snapshot(&self) -> SearchStats {
+        SearchStats {
+            elapsed: self.elapsed(),
+            ..self.stats.lock().unwrap().clone()
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.started
+            .lock()
+            .unwrap()
+            .map_or(Duration::ZERO, |started| started.elapsed())
+    }
+
+    /// Clears the totals for a new search
+    fn restart(&self) -> Instant {
+        let started = Instant::now();
+        *self.stats.lock().unwrap() = SearchStats::default();
+        *self.started.lock().unwrap() = Some(started);
+        started
+    }
+
+    fn add(&self, stats: SearchStats) {
+        self.stats.lock().unwrap().merge(&stats);
+    }
+
+    /// The limit the totals have reached, if any
+    fn reached(&self, limits: &SearchLimits) -> Option<Limit> {
+        let elapsed = self.elapsed();
+        let stats = self.stats.lock().unwrap();
+        limits
+            .counts_reached(&stats)
+            .or_else(|| limits.clock_reached(elapsed))
+    }
+}
+
+/// Searches with `State` on several threads, either for the first schedule any thread finds or
+/// for every schedule
+#[derive(Clone, Debug)]
+pub struct ParallelState<S = u32> {
+    state: State<S>,
+    threads: usize,
+    limits: SearchLimits,
+    stats: Arc<ParallelStats>,
+}
+
+impl<S: PlayerSet> ParallelState<S> {
+    /// Uses as many threads as the machine has cores
+    pub fn new(config: Config) -> Result<Self, ConfigError> {
+        let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+        Ok(Self {
+            state: State::new(config)?,
+            threads,
+            limits: SearchLimits::default(),
+            stats: Arc::default(),
+        })
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Stops `find` once any is reached, checking every few thousand steps of each thread
+    pub fn with_limits(mut self, limits: SearchLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        self.state.get_config()
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Shared with the threads, so progress can be followed from elsewhere
+    pub fn stats(&self) -> Arc<ParallelStats> {
+        Arc::clone(&self.stats)
+    }
+
+    /// The first schedule any thread finds, which need not be the first in search order
+    pub fn find(&self) -> SearchOutcome {
+        self.find_observed(&NoObserver)
+    }
+
+    /// Like `find`, giving each thread its own clone of `observer` to tell what it does. The
+    /// players each state adds to the one it was stepped from are reported as placed, but as
+    /// threads take states from each other nothing is reported as removed or backtracked
+    pub fn find_observed<O: SearchObserver + Send + Clone>(&self, observer: &O) -> SearchOutcome {
+        let found = Mutex::new(None);
+        let stop = self.run(Some(&self.limits), observer, |schedule| {
+            found.lock().unwrap().get_or_insert(schedule);
+            false
+        });
+        stop.outcome(found.into_inner().unwrap(), &self.stats)
+    }
+
+    /// Counts every schedule, like `count_solutions` on `State::solutions`. The limits are not
+    /// applied
+    pub fn count(&self, filter: Option<IsomorphismFilter>) -> SolutionCount {
+        self.count_observed(filter, &NoObserver)
+    }
+
+    /// Like `count`, telling each thread's clone of `observer` what it does as `find_observed`
+    /// does
+    pub fn count_observed<O: SearchObserver + Send + Clone>(
+        &self,
+        filter: Option<IsomorphismFilter>,
+        observer: &O,
+    ) -> SolutionCount {
+        let start = Instant::now();
+        let solutions = AtomicU64::new(0);
+        let unique = AtomicU64::new(0);
+        let filter = filter.map(Mutex::new);
+        self.run(None, observer, |schedule| {
+            solutions.fetch_add(1, Ordering::Relaxed);
+            if let Some(filter) = filter.as_ref() {
+                if filter.lock().unwrap().insert_schedule(schedule.rounds()) {
+                    unique.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            true
+        });
+        SolutionCount {
+            solutions: solutions.into_inner(),
+            unique: filter.map(|_| unique.into_inner()),
+            elapsed: start.elapsed(),
+        }
+    }
+
+    /// Explores until every state has been stepped, `found` returns false or a limit is reached
+    fn run<O, F>(&self, limits: Option<&SearchLimits>, observer: &O, found: F) -> Stop
+    where
+        O: SearchObserver + Send + Clone,
+        F: Fn(Schedule) -> bool + Sync,
+    {
+        self.stats.restart();
+        let stacks: Vec<Mutex<VecDeque<State<S>>>> = (0..self.threads)
+            .map(|_| Mutex::new(VecDeque::new()))
+            .collect();
+        stacks[0].lock().unwrap().push_back(self.state.clone());
+        // States on the stacks or being stepped, so idle threads know when the search is over
+        let pending = AtomicUsize::new(1);
+        let idle = Idle::default();
+        let stop = Stop::default();
+        std::thread::scope(|scope| {
+            for thread in 0..self.threads {
+                let (stacks, pending, idle, stop, found) =
+                    (&stacks, &pending, &idle, &stop, &found);
+                let mut observer = observer.clone();
+                scope.spawn(move || {
+                    let mut stats = SearchStats::default();
+                    let mut state2 = self.state.clone();
+                    while !stop.is_stopped() {
+                        let mut state = match pop_or_steal(stacks, thread) {
+                            Some(state) => state,
+                            None if pending.load(Ordering::Acquire) == 0 => break,
+                            None => {
+                                let ready = || {
+                                    pending.load(Ordering::Acquire) == 0 || stop.is_stopped()
+                                };
+                                match idle.wait(|| pop_or_steal(stacks, thread), ready) {
+                                    Some(state) => state,
+                                    None => continue,
+                                }
+                            }
+                        };
+                        let before = if observer.watches_players() {
+                            Some(state.clone())
+                        } else {
+                            None
+                        };
+                        let result = state.step(&mut state2);
+                        if let Some(before) = &before {
+                            let after = match result {
+                                Ok(Some(())) => &state2,
+                                _ => &state,
+                            };
+                            after.for_each_player_not_in(before, |round, table, player| {
+                                observer.placed(round, table, player)
+                            });
+                        }
+                        match result {
+                            Ok(Some(())) => {
+                                stats.record(state2.get_players_played_count() as usize, || {
+                                    state2.schedule()
+                                });
+                                pending.fetch_add(2, Ordering::Relaxed);
+                                let mut stack = stacks[thread].lock().unwrap();
+                                stack.push_back(state);
+                                stack.push_back(state2.clone());
+                                drop(stack);
+                                idle.work_added();
+                            }
+                            Ok(None) => {
+                                stats.record(state.get_players_played_count() as usize, || {
+                                    state.schedule()
+                                });
+                                stats.solutions += 1;
+                                let schedule = state.schedule();
+                                observer.solution_found(&schedule);
+                                if !found(schedule) {
+                                    stop.stop();
+                                    idle.wake_all();
+                                }
+                            }
+                            Err(_) => {
+                                stats.record(state.get_players_played_count() as usize, || {
+                                    state.schedule()
+                                });
+                                stats.backtracks += 1;
+                                observer.dead_end(state.get_players_played_count() as usize);
+                            }
+                        }
+                        if pending.fetch_sub(1, Ordering::AcqRel) == 1 {
+                            idle.wake_all();
+                        }
+                        if stats.nodes % CHECK_INTERVAL != 0 {
+                            continue;
+                        }
+                        self.stats.add(std::mem::take(&mut stats));
+                        if let Some(limit) = limits.and_then(|limits| self.stats.reached(limits)) {
+                            stop.reach(limit);
+                            idle.wake_all();
+                        }
+                    }
+                    self.stats.add(stats);
+                });
+            }
+        });
+        stop
+    }
+}
+
+/// Where threads with nothing to steal sleep until there is work or the search is over
+#[derive(Default)]
+struct Idle {
+    /// Threads asleep or about to sleep, so that threads adding work only wake them if needed
+    sleeping: AtomicUsize,
+    /// Counts wake ups, so a sleeping thread can tell it was woken since it last looked for work
+    wakes: Mutex<u64>,
+    woken: Condvar,
+}
+
+impl Idle {
+    /// Looks for work once more then sleeps until woken, returning the work if found. Returns
+    /// `None` once woken or if `ready` says there is no need to sleep
+    fn wait<T>(&self, mut take: impl FnMut() -> Option<T>, ready: impl Fn() -> bool) -> Option<T> {
+        self.sleeping.fetch_add(1, Ordering::SeqCst);
+        let seen = *self.wakes.lock().unwrap();
+        // Work added before `sleeping` went up is found here, and work added after wakes us
+        let work = take();
+        if work.is_none() {
+            let mut wakes = self.wakes.lock().unwrap();
+            while *wakes == seen && !ready() {
+                wakes = self.woken.wait(wakes).unwrap();
+            }
+        }
+        self.sleeping.fetch_sub(1, Ordering::SeqCst);
+        work
+    }
+
+    fn work_added(&self) {
+        if self.sleeping.load(Ordering::SeqCst) > 0 {
+            self.wake_all();
+        }
+    }
+
+    fn wake_all(&self) {
+        *self.wakes.lock().unwrap() += 1;
+        self.woken.notify_all();
+    }
+}
+
+/// Takes the deepest state from the thread's own stack, or failing that the shallowest from
+/// another's
+fn pop_or_steal<S>(stacks: &[Mutex<VecDeque<State<S>>>], thread: usize) -> Option<State<S>> {
+    if let Some(state) = stacks[thread].lock().unwrap().pop_back() {
+        return Some(state);
+    }
+    (1..stacks.len())
+        .map(|offset| (thread + offset) % stacks.len())
+        .find_map(|victim| stacks[victim].lock().unwrap().pop_front())
+}
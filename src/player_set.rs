@@ -0,0 +1,244 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use core::fmt::Debug;
+use core::hash::Hash;
+use core::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+
+/// Bitset of player numbers, one bit per player
+///
+/// Implemented for the unsigned integers so small events keep the single instruction
+/// `trailing_zeros`/`count_ones` paths, and for `WidePlayerSet` when more than 128 players are needed
+pub trait PlayerSet:
+    Copy
+    + Debug
+    + Default
+    + Eq
+    + Hash
+    + Send
+    + Sync
+    + Serialize
+    + DeserializeOwned
+    + BitAnd<Output = Self>
+    + BitAndAssign
+    + BitOr<Output = Self>
+    + BitOrAssign
+    + BitXor<Output = Self>
+    + BitXorAssign
+    + Not<Output = Self>
+    + 'static
+{
+    /// Largest number of players that can be stored
+    const CAPACITY: usize;
+
+    fn empty() -> Self;
+
+    /// Players `0..n`
+    fn first_n(n: usize) -> Self;
+
+    fn single(player: usize) -> Self;
+
+    fn count(&self) -> u32;
+
+    /// Lowest player in the set
+    fn lowest(&self) -> Option<usize>;
+
+    fn is_empty(&self) -> bool {
+        *self == Self::empty()
+    }
+
+    fn contains(&self, player: usize) -> bool {
+        !(*self & Self::single(player)).is_empty()
+    }
+
+    fn insert(&mut self, player: usize) {
+        *self |= Self::single(player);
+    }
+
+    fn remove(&mut self, player: usize) {
+        *self &= !Self::single(player);
+    }
+
+    /// Players `0..=player`
+    fn up_to(player: usize) -> Self {
+        Self::first_n(player + 1)
+    }
+
+    fn pop_lowest(&mut self) -> Option<usize> {
+        let player = self.lowest()?;
+        self.remove(player);
+        Some(player)
+    }
+
+    /// Iterates over the players in ascending order
+    fn iter(self) -> PlayerIter<Self> {
+        PlayerIter(self)
+    }
+}
+
+pub struct PlayerIter<S>(S);
+
+impl<S: PlayerSet> Iterator for PlayerIter<S> {
+    type Item = usize;
+    fn next(&mut self) -> Option<usize> {
+        self.0.pop_lowest()
+    }
+}
+
+macro_rules! impl_player_set {
+    ($($t:ty),*) => {
+        $(
+            impl PlayerSet for $t {
+                const CAPACITY: usize = <$t>::BITS as usize;
+
+                fn empty() -> Self {
+                    0
+                }
+
+                fn first_n(n: usize) -> Self {
+                    if n >= Self::CAPACITY {
+                        !0
+                    } else {
+                        (1 << n) - 1
+                    }
+                }
+
+                fn single(player: usize) -> Self {
+                    1 << player
+                }
+
+                fn count(&self) -> u32 {
+                    self.count_ones()
+                }
+
+                fn lowest(&self) -> Option<usize> {
+                    if *self == 0 {
+                        None
+                    } else {
+                        Some(self.trailing_zeros() as usize)
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_player_set!(u32, u64, u128);
+
+/// Player set made of `WORDS` 64 bit words, for events too large for `u128`
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct WidePlayerSet<const WORDS: usize>([u64; WORDS]);
+
+impl<const WORDS: usize> Default for WidePlayerSet<WORDS> {
+    fn default() -> Self {
+        Self([0; WORDS])
+    }
+}
+
+impl<const WORDS: usize> PlayerSet for WidePlayerSet<WORDS> {
+    const CAPACITY: usize = WORDS * 64;
+
+    fn empty() -> Self {
+        Self([0; WORDS])
+    }
+
+    fn first_n(n: usize) -> Self {
+        let mut set = Self::empty();
+        for (i, word) in set.0.iter_mut().enumerate() {
+            *word = u64::first_n(n.saturating_sub(i * 64));
+        }
+        set
+    }
+
+    fn single(player: usize) -> Self {
+        let mut set = Self::empty();
+        set.0[player / 64] = 1 << (player % 64);
+        set
+    }
+
+    fn count(&self) -> u32 {
+        self.0.iter().map(|word| word.count_ones()).sum()
+    }
+
+    fn lowest(&self) -> Option<usize> {
+        self.0
+            .iter()
+            .enumerate()
+            .find(|(_, word)| **word != 0)
+            .map(|(i, word)| i * 64 + word.trailing_zeros() as usize)
+    }
+}
+
+macro_rules! impl_wide_op {
+    ($op:ident, $method:ident, $op_assign:ident, $method_assign:ident) => {
+        impl<const WORDS: usize> $op for WidePlayerSet<WORDS> {
+            type Output = Self;
+            fn $method(mut self, rhs: Self) -> Self::Output {
+                self.$method_assign(rhs);
+                self
+            }
+        }
+
+        impl<const WORDS: usize> $op_assign for WidePlayerSet<WORDS> {
+            fn $method_assign(&mut self, rhs: Self) {
+                for (word, rhs) in self.0.iter_mut().zip(rhs.0.iter()) {
+                    word.$method_assign(*rhs);
+                }
+            }
+        }
+    };
+}
+
+impl_wide_op!(BitAnd, bitand, BitAndAssign, bitand_assign);
+impl_wide_op!(BitOr, bitor, BitOrAssign, bitor_assign);
+impl_wide_op!(BitXor, bitxor, BitXorAssign, bitxor_assign);
+
+impl<const WORDS: usize> Not for WidePlayerSet<WORDS> {
+    type Output = Self;
+    fn not(mut self) -> Self {
+        for word in self.0.iter_mut() {
+            *word = !*word;
+        }
+        self
+    }
+}
+
+impl<const WORDS: usize> Serialize for WidePlayerSet<WORDS> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeTuple;
+        let mut tuple = serializer.serialize_tuple(WORDS)?;
+        for word in self.0.iter() {
+            tuple.serialize_element(word)?;
+        }
+        tuple.end()
+    }
+}
+
+impl<'de, const WORDS: usize> Deserialize<'de> for WidePlayerSet<WORDS> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor<const WORDS: usize>;
+
+        impl<'de, const WORDS: usize> serde::de::Visitor<'de> for Visitor<WORDS> {
+            type Value = WidePlayerSet<WORDS>;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                write!(formatter, "{} words", WORDS)
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut set = WidePlayerSet::empty();
+                for (i, word) in set.0.iter_mut().enumerate() {
+                    *word = seq
+                        .next_element()?
+                        .ok_or_else(|| serde::de::Error::invalid_length(i, &self))?;
+                }
+                Ok(set)
+            }
+        }
+
+        deserializer.deserialize_tuple(WORDS, Visitor::<WORDS>)
+    }
+}
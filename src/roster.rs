@@ -0,0 +1,225 @@
+//! Names for players, so schedules can be printed for people rather than player numbers
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::config::MAX_COUNT;
+use crate::import::split_csv_line;
+
+#[derive(Debug, Error)]
+pub enum RosterError {
+    #[error("Line {line}: the header has no {column:?} column")]
+    MissingColumn { line: usize, column: &'static str },
+    #[error("Line {line} has {found} cells but {expected} were expected")]
+    WrongCellCount {
+        line: usize,
+        found: usize,
+        expected: usize,
+    },
+    #[error("Line {line}: a quoted cell is never closed")]
+    UnclosedQuote { line: usize },
+    #[error("Line {line}, cell {cell}: {text:?} is not a valid player id")]
+    BadId {
+        line: usize,
+        cell: usize,
+        text: String,
+    },
+    #[error("Line {line}, cell {cell}: player id {id} is more than the largest allowed, {max}")]
+    IdTooLarge {
+        line: usize,
+        cell: usize,
+        id: usize,
+        max: usize,
+    },
+    #[error("Line {line}: player id {id} is already used")]
+    DuplicateId { line: usize, id: usize },
+    #[error("No player has id {id}, but ids must run from 0 without gaps")]
+    MissingId { id: usize },
+    #[error("The name {name:?} is used more than once")]
+    DuplicateName { name: String },
+    #[error("The name {name:?} spans more than one line")]
+    MultilineName { name: String },
+    #[error("No player is called {name:?}")]
+    UnknownName { name: String },
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Player {
+    pub name: String,
+    /// Anything else known about the player, such as a club or contact details
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Players indexed by player number, with distinct names on a single line each
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RosterData")]
+pub struct Roster {
+    players: Vec<Player>,
+}
+
+/// A `Roster` as read, before its names are checked
+#[derive(Deserialize)]
+struct RosterData {
+    players: Vec<Player>,
+}
+
+impl TryFrom<RosterData> for Roster {
+    type Error = RosterError;
+
+    fn try_from(data: RosterData) -> Result<Self, RosterError> {
+        Self::from_players(data.players)
+    }
+}
+
+impl Roster {
+    /// Numbers the players in the order given
+    pub fn new<I>(names: I) -> Result<Self, RosterError>
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        Self::from_players(
+            names
+                .into_iter()
+                .map(|name| Player {
+                    name: name.into(),
+                    metadata: BTreeMap::new(),
+                })
+                .collect(),
+        )
+    }
+
+    /// Names may not contain line breaks, as the CSV readers take each line as a row
+    pub fn from_players(players: Vec<Player>) -> Result<Self, RosterError> {
+        if let Some(player) = players.iter().find(|player| player.name.contains(['\n', '\r'])) {
+            return Err(RosterError::MultilineName {
+                name: player.name.clone(),
+            });
+        }
+        let mut names: Vec<&str> = players.iter().map(|player| player.name.as_str()).collect();
+        names.sort_unstable();
+        if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(RosterError::DuplicateName {
+                name: pair[0].to_string(),
+            });
+        }
+        Ok(Self { players })
+    }
+
+    /// Reads a header line with a `name` column, then a line per player. An `id` column numbers
+    /// the players, otherwise they are numbered in the order listed. Any other columns are kept
+    /// as metadata
+    pub fn from_csv(input: &str) -> Result<Self, RosterError> {
+        let mut lines = input
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line))
+            .filter(|(_, line)| !line.trim().is_empty());
+        let (header_line, header) = match lines.next() {
+            Some((line, text)) => (
+                line,
+                split_csv_line(text).ok_or(RosterError::UnclosedQuote { line })?,
+            ),
+            None => (1, Vec::new()),
+        };
+        let name_column = header
+            .iter()
+            .position(|column| column == "name")
+            .ok_or(RosterError::MissingColumn {
+                line: header_line,
+                column: "name",
+            })?;
+        let id_column = header.iter().position(|column| column == "id");
+
+        let mut players: Vec<Option<Player>> = Vec::new();
+        for (index, (line, text)) in lines.enumerate() {
+            let cells = split_csv_line(text).ok_or(RosterError::UnclosedQuote { line })?;
+            if cells.len() != header.len() {
+                return Err(RosterError::WrongCellCount {
+                    line,
+                    found: cells.len(),
+                    expected: header.len(),
+                });
+            }
+            let id = match id_column {
+                Some(column) => {
+                    let id = cells[column].parse().map_err(|_| RosterError::BadId {
+                        line,
+                        cell: column + 1,
+                        text: cells[column].clone(),
+                    })?;
+                    // Bounded as in `Config`, before the players are sized by it
+                    if id >= MAX_COUNT {
+                        return Err(RosterError::IdTooLarge {
+                            line,
+                            cell: column + 1,
+                            id,
+                            max: MAX_COUNT - 1,
+                        });
+                    }
+                    id
+                }
+                None => index,
+            };
+            if players.len() <= id {
+                players.resize(id + 1, None);
+            }
+            if players[id].is_some() {
+                return Err(RosterError::DuplicateId { line, id });
+            }
+            let mut player = Player::default();
+            for (column, cell) in cells.into_iter().enumerate() {
+                if column == name_column {
+                    player.name = cell;
+                } else if Some(column) != id_column {
+                    player.metadata.insert(header[column].clone(), cell);
+                }
+            }
+            players[id] = Some(player);
+        }
+        let players = players
+            .into_iter()
+            .enumerate()
+            .map(|(id, player)| player.ok_or(RosterError::MissingId { id }))
+            .collect::<Result<_, _>>()?;
+        Self::from_players(players)
+    }
+
+    pub fn len(&self) -> usize {
+        self.players.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.players.is_empty()
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    pub fn get(&self, player: usize) -> Option<&Player> {
+        self.players.get(player)
+    }
+
+    /// Player number of the player with this name
+    pub fn id(&self, name: &str) -> Result<usize, RosterError> {
+        self.players
+            .iter()
+            .position(|player| player.name == name)
+            .ok_or_else(|| RosterError::UnknownName {
+                name: name.to_string(),
+            })
+    }
+
+    /// Name of the player, or their number if they are not on the roster
+    pub fn label(&self, player: usize) -> String {
+        match self.players.get(player) {
+            Some(player) => player.name.clone(),
+            None => player.to_string(),
+        }
+    }
+}
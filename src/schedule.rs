@@ -0,0 +1,267 @@
+//! Finished schedules independent of the solver that produced them, and checking them against a
+//! config
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::sit_out::SitOuts;
+use crate::{Config, PlayerSet, WidePlayerSet};
+
+/// Players at each table, indexed by round then table
+pub type ScheduleGrid = Vec<Vec<Vec<u8>>>;
+
+/// Large enough for every player number a `u8` can hold
+type Players = WidePlayerSet<4>;
+
+/// Finished schedule from either solver, or written by hand
+///
+/// Serialises as `{"rounds": [...]}`, which is the schema `JsonExporter` promises to keep
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Schedule {
+    rounds: ScheduleGrid,
+}
+
+/// A way in which a schedule breaks its config, with indices starting from 0
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+pub enum Violation {
+    #[error("There are {round_count} rounds but {expected} were expected")]
+    WrongRoundCount { round_count: usize, expected: usize },
+    #[error("Round {round} has {table_count} tables but {expected} were expected")]
+    WrongTableCount {
+        round: usize,
+        table_count: usize,
+        expected: usize,
+    },
+    #[error("Table {table} in round {round} seats {seats} players but should seat {expected}")]
+    WrongTableSize {
+        round: usize,
+        table: usize,
+        seats: usize,
+        expected: usize,
+    },
+    #[error("Seat {seat} at table {table} in round {round} has unknown player {player}")]
+    UnknownPlayer {
+        round: usize,
+        table: usize,
+        seat: usize,
+        player: usize,
+    },
+    #[error("Player {player} is at table {first_table} and table {second_table} in round {round}")]
+    TwiceInRound {
+        player: usize,
+        round: usize,
+        first_table: usize,
+        second_table: usize,
+    },
+    #[error("Player {player} is at table {table} in rounds {first_round} and {second_round}")]
+    TableRevisited {
+        player: usize,
+        table: usize,
+        first_round: usize,
+        second_round: usize,
+    },
+    #[error(
+        "Players {first} and {second} meet at table {table} in round {round} after already \
+         meeting in round {earlier_round}"
+    )]
+    RepeatMeeting {
+        first: usize,
+        second: usize,
+        round: usize,
+        table: usize,
+        earlier_round: usize,
+    },
+    #[error("Player {player} is available in round {round} but does not play")]
+    MissingPlayer { player: usize, round: usize },
+    #[error("Player {player} plays at table {table} in round {round} but is not available")]
+    Unavailable {
+        player: usize,
+        round: usize,
+        table: usize,
+    },
+    #[error("Player {player} is pinned to table {table} in round {round} but is not there")]
+    PinIgnored {
+        player: usize,
+        round: usize,
+        table: usize,
+    },
+    #[error("Players {first} and {second} meet at table {table} in round {round} but may not")]
+    ForbiddenPair {
+        first: usize,
+        second: usize,
+        round: usize,
+        table: usize,
+    },
+    #[error("Players {first} and {second} must meet but never do")]
+    PairNeverMet { first: usize, second: usize },
+    #[error("Someone sits out in round {round} who has sat out more often than someone playing")]
+    UnfairSitOuts { round: usize },
+}
+
+impl Schedule {
+    pub fn new(rounds: ScheduleGrid) -> Self {
+        Self { rounds }
+    }
+
+    pub fn round_count(&self) -> usize {
+        self.rounds.len()
+    }
+
+    /// Players at each table in a round
+    pub fn round(&self, round: usize) -> &[Vec<u8>] {
+        &self.rounds[round]
+    }
+
+    pub fn rounds(&self) -> &ScheduleGrid {
+        &self.rounds
+    }
+
+    pub fn into_rounds(self) -> ScheduleGrid {
+        self.rounds
+    }
+
+    /// Every way the schedule breaks the config, in the order they are found
+    pub fn validate(&self, config: &Config) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        if self.rounds.len() != config.round_count() {
+            violations.push(Violation::WrongRoundCount {
+                round_count: self.rounds.len(),
+                expected: config.round_count(),
+            });
+        }
+        let player_count = config.player_count();
+        // Round each pair first met in, and round each player was first at each table
+        let mut met: Vec<Vec<Option<usize>>> = vec![vec![None; player_count]; player_count];
+        let mut visited: Vec<Vec<Option<usize>>> =
+            vec![vec![None; config.table_count()]; player_count];
+        let mut sit_outs = SitOuts::new(player_count);
+        for (round, tables) in config.rounds().zip(self.rounds.iter()) {
+            let r = round.index();
+            if tables.len() != config.table_count() {
+                violations.push(Violation::WrongTableCount {
+                    round: r,
+                    table_count: tables.len(),
+                    expected: config.table_count(),
+                });
+            }
+            let mut seated_at: Vec<Option<usize>> = vec![None; player_count];
+            for (table, players) in config.tables().zip(tables.iter()) {
+                let t = table.index();
+                if players.len() != config.seats(round, table) {
+                    violations.push(Violation::WrongTableSize {
+                        round: r,
+                        table: t,
+                        seats: players.len(),
+                        expected: config.seats(round, table),
+                    });
+                }
+                for (seat, &player) in players.iter().enumerate() {
+                    let player = player as usize;
+                    if player >= player_count {
+                        violations.push(Violation::UnknownPlayer {
+                            round: r,
+                            table: t,
+                            seat,
+                            player,
+                        });
+                        continue;
+                    }
+                    if let Some(first_table) = seated_at[player] {
+                        violations.push(Violation::TwiceInRound {
+                            player,
+                            round: r,
+                            first_table,
+                            second_table: t,
+                        });
+                        continue;
+                    }
+                    seated_at[player] = Some(t);
+                    if !config.is_available(player, round) {
+                        violations.push(Violation::Unavailable {
+                            player,
+                            round: r,
+                            table: t,
+                        });
+                    }
+                    match visited[player][t] {
+                        Some(first_round) => violations.push(Violation::TableRevisited {
+                            player,
+                            table: t,
+                            first_round,
+                            second_round: r,
+                        }),
+                        None => visited[player][t] = Some(r),
+                    }
+                }
+                let mut known: Vec<usize> = players
+                    .iter()
+                    .map(|&player| player as usize)
+                    .filter(|&player| player < player_count)
+                    .collect();
+                known.sort_unstable();
+                known.dedup();
+                for (i, &first) in known.iter().enumerate() {
+                    for &second in known[i + 1..].iter() {
+                        if config.forbidden_pairs().contains(&(first, second)) {
+                            violations.push(Violation::ForbiddenPair {
+                                first,
+                                second,
+                                round: r,
+                                table: t,
+                            });
+                        }
+                        match met[first][second] {
+                            Some(earlier_round) => violations.push(Violation::RepeatMeeting {
+                                first,
+                                second,
+                                round: r,
+                                table: t,
+                                earlier_round,
+                            }),
+                            None => met[first][second] = Some(r),
+                        }
+                    }
+                }
+                for &player in config.pinned(round, table).iter() {
+                    if seated_at[player] != Some(t) {
+                        violations.push(Violation::PinIgnored {
+                            player,
+                            round: r,
+                            table: t,
+                        });
+                    }
+                }
+            }
+
+            let mut played = Players::empty();
+            for player in (0..player_count).filter(|&player| seated_at[player].is_some()) {
+                played.insert(player);
+            }
+            let available: Players = config.available(round);
+            if config.sit_out_count(round) == 0 {
+                for player in (available & !played).iter() {
+                    violations.push(Violation::MissingPlayer { player, round: r });
+                }
+            } else if !sit_outs.is_fair(available, played & available) {
+                violations.push(Violation::UnfairSitOuts { round: r });
+            }
+            sit_outs.record(played);
+        }
+        for &(first, second) in config.must_meet_pairs().iter() {
+            if met[first][second].is_none() {
+                violations.push(Violation::PairNeverMet { first, second });
+            }
+        }
+        violations
+    }
+
+    pub fn is_valid(&self, config: &Config) -> bool {
+        self.validate(config).is_empty()
+    }
+}
+
+impl From<ScheduleGrid> for Schedule {
+    fn from(rounds: ScheduleGrid) -> Self {
+        Self::new(rounds)
+    }
+}
@@ -0,0 +1,62 @@
+//! Keeping sit-outs fair when there are more available players than seats in a round
+//!
+//! In every round the players who sit out must be those who have sat out least so far, so
+//! nobody sits out twice before everyone has sat out once. Rounds a player is absent for count
+//! as sitting out.
+
+use crate::PlayerSet;
+
+#[derive(Clone, Debug)]
+pub(crate) struct SitOuts {
+    counts: Vec<u8>,
+}
+
+impl SitOuts {
+    pub(crate) fn new(player_count: usize) -> Self {
+        Self {
+            counts: vec![0; player_count],
+        }
+    }
+
+    /// Records a filled round, where everyone who did not play sat out
+    pub(crate) fn record<S: PlayerSet>(&mut self, played: S) {
+        let sitting_out = S::first_n(self.counts.len()) & !played;
+        for player in sitting_out.iter() {
+            self.counts[player] += 1;
+        }
+    }
+
+    /// Whether nobody who played has sat out fewer times than an available player sitting out
+    pub(crate) fn is_fair<S: PlayerSet>(&self, available: S, played: S) -> bool {
+        let most_sitting_out = (available & !played)
+            .iter()
+            .map(|player| self.counts[player])
+            .max();
+        let fewest_playing = played.iter().map(|player| self.counts[player]).min();
+        match (most_sitting_out, fewest_playing) {
+            (Some(sitting_out), Some(playing)) => sitting_out <= playing,
+            _ => true,
+        }
+    }
+
+    /// Players who must sit out and players who must play in a round where `sit_out_count` of
+    /// the available players sit out
+    pub(crate) fn forced<S: PlayerSet>(&self, available: S, sit_out_count: usize) -> (S, S) {
+        if sit_out_count == 0 {
+            return (S::empty(), available);
+        }
+        let mut counts: Vec<u8> = available.iter().map(|player| self.counts[player]).collect();
+        counts.sort_unstable();
+        let threshold = counts[sit_out_count - 1];
+        let mut must_sit_out = S::empty();
+        let mut must_play = S::empty();
+        for player in available.iter() {
+            match self.counts[player].cmp(&threshold) {
+                core::cmp::Ordering::Less => must_sit_out.insert(player),
+                core::cmp::Ordering::Equal => {}
+                core::cmp::Ordering::Greater => must_play.insert(player),
+            }
+        }
+        (must_sit_out, must_play)
+    }
+}
@@ -0,0 +1,381 @@
+//! Enumerating every schedule a search can reach, rather than stopping at the first
+//!
+//! Both searches already skip schedules that only differ by relabelling players where they can,
+//! so the plain count is of the schedules they yield. Counting with an `IsomorphismFilter` also
+//! merges schedules that only differ by reordering rounds or tables.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::limits::{self, Stepwise};
+use crate::{
+    Config, IsomorphismFilter, NoObserver, PlayerSet, Progress, Schedule, SearchLimits,
+    SearchObserver, SearchOutcome, SearchStats, State, StepError, DF2,
+};
+
+/// What came of one step of a solution iterator
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SearchStep {
+    /// Nothing found yet, but there is more to search
+    Searching,
+    Found(Schedule),
+    /// Every schedule has been found
+    Finished,
+}
+
+/// Iterator over every complete schedule a `DF2` search finds
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DF2Solutions<S = u32> {
+    search: DF2<S>,
+    finished: bool,
+    /// Since the search was made or resumed, so not saved in checkpoints
+    #[serde(skip)]
+    stats: SearchStats,
+    #[serde(skip, default = "Instant::now")]
+    started: Instant,
+}
+
+impl<S: PlayerSet> DF2Solutions<S> {
+    pub(crate) fn new(search: DF2<S>) -> Self {
+        Self {
+            search,
+            finished: false,
+            stats: SearchStats::default(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Takes a single step of the search, so callers can stop between steps
+    pub fn step(&mut self) -> SearchStep {
+        self.step_observed(&mut NoObserver)
+    }
+
+    /// Like `step`, telling `observer` what the search does
+    pub fn step_observed<O: SearchObserver>(&mut self, observer: &mut O) -> SearchStep {
+        if self.finished {
+            return SearchStep::Finished;
+        }
+        let placed = self.search.get_players_placed() as u64;
+        let result = self.search.step_observed(observer);
+        let search = &self.search;
+        let now_placed = search.get_players_placed() as u64;
+        // A step backtracks some number of times and then places a player unless it fails
+        self.stats.backtracks += placed + result.is_ok() as u64 - now_placed;
+        self.stats.record(now_placed as usize, || search.schedule());
+        match result {
+            Ok(()) => SearchStep::Searching,
+            Err(StepError::FinishedStepping(_)) => {
+                let schedule = self.search.schedule();
+                self.stats.solutions += 1;
+                observer.solution_found(&schedule);
+                // Move on so the next step looks for a different schedule
+                self.finished = self.search.backtrack_observed(observer).is_err();
+                self.stats.backtracks += !self.finished as u64;
+                SearchStep::Found(schedule)
+            }
+            Err(StepError::ExceededMaxBacktrack(_)) => {
+                self.finished = true;
+                SearchStep::Finished
+            }
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        self.search.get_config()
+    }
+
+    /// Progress since the search was made or resumed
+    pub fn stats(&self) -> SearchStats {
+        SearchStats {
+            elapsed: self.started.elapsed(),
+            ..self.stats.clone()
+        }
+    }
+
+    /// Progress without the elapsed time, cheap enough to read every step
+    pub fn counts(&self) -> &SearchStats {
+        &self.stats
+    }
+
+    /// Stats gathered since last taken, for adding to those of searches run alongside
+    pub(crate) fn take_stats(&mut self) -> SearchStats {
+        let mut stats = std::mem::take(&mut self.stats);
+        stats.elapsed = self.started.elapsed();
+        stats
+    }
+
+    /// Steps until a schedule is found, the search finishes or a limit is reached. Limits count
+    /// from when the search was made or resumed
+    pub fn find(&mut self, limits: &SearchLimits) -> SearchOutcome {
+        match limits::find(self, limits) {
+            Ok(outcome) => outcome,
+            Err(never) => match never {},
+        }
+    }
+
+    pub(crate) fn fits_config(&self) -> bool {
+        self.search.fits_config()
+    }
+}
+
+impl<S: PlayerSet> Stepwise for DF2Solutions<S> {
+    type Error = std::convert::Infallible;
+
+    fn step(&mut self) -> Result<SearchStep, Self::Error> {
+        Ok(DF2Solutions::step(self))
+    }
+
+    fn counts(&self) -> &SearchStats {
+        &self.stats
+    }
+
+    fn started(&self) -> Instant {
+        self.started
+    }
+}
+
+impl<S: PlayerSet> Iterator for DF2Solutions<S> {
+    type Item = Schedule;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.step() {
+                SearchStep::Searching => {}
+                SearchStep::Found(schedule) => return Some(schedule),
+                SearchStep::Finished => return None,
+            }
+        }
+    }
+}
+
+/// Iterator over every complete schedule a `State` search finds, exploring depth first
+#[derive(Clone, Debug, Deserialize)]
+#[serde(from = "SavedStateSolutions<S>")]
+pub struct StateSolutions<S = u32> {
+    stack: Vec<State<S>>,
+    state2: State<S>,
+    /// Since the search was made or resumed, so not saved in checkpoints
+    stats: SearchStats,
+    started: Instant,
+}
+
+/// How a `StateSolutions` is saved, with the config its states share written once rather than
+/// with every state on the stack
+#[derive(Serialize)]
+struct SavedStateSolutionsRef<'a, S> {
+    config: &'a Config,
+    stack: Vec<&'a Progress<S>>,
+    state2: &'a Progress<S>,
+}
+
+#[derive(Deserialize)]
+struct SavedStateSolutions<S> {
+    config: Config,
+    stack: Vec<Progress<S>>,
+    state2: Progress<S>,
+}
+
+impl<S: Serialize> Serialize for StateSolutions<S> {
+    fn serialize<Z: Serializer>(&self, serializer: Z) -> Result<Z::Ok, Z::Error> {
+        SavedStateSolutionsRef {
+            config: &self.state2.config,
+            stack: self.stack.iter().map(|state| &state.progress).collect(),
+            state2: &self.state2.progress,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<S> From<SavedStateSolutions<S>> for StateSolutions<S> {
+    fn from(saved: SavedStateSolutions<S>) -> Self {
+        let config = Arc::new(saved.config);
+        let state = |progress| State {
+            config: Arc::clone(&config),
+            progress,
+        };
+        Self {
+            stack: saved.stack.into_iter().map(state).collect(),
+            state2: state(saved.state2),
+            stats: SearchStats::default(),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl<S: PlayerSet> StateSolutions<S> {
+    pub(crate) fn new(state: State<S>) -> Self {
+        Self {
+            state2: state.clone(),
+            stack: vec![state],
+            stats: SearchStats::default(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Takes a single step of the search, so callers can stop between steps
+    pub fn step(&mut self) -> SearchStep {
+        self.step_observed(&mut NoObserver)
+    }
+
+    /// Like `step`, telling `observer` what the search does. Placements and removals are found by
+    /// comparing each state with the one it was stepped from or returned to
+    pub fn step_observed<O: SearchObserver>(&mut self, observer: &mut O) -> SearchStep {
+        let mut state = match self.stack.pop() {
+            Some(state) => state,
+            None => return SearchStep::Finished,
+        };
+        let before = if observer.watches_players() {
+            Some(state.clone())
+        } else {
+            None
+        };
+        let result = state.step(&mut self.state2);
+        if let Some(before) = &before {
+            let after = match result {
+                Ok(Some(())) => &self.state2,
+                _ => &state,
+            };
+            after.for_each_player_not_in(before, |round, table, player| {
+                observer.placed(round, table, player)
+            });
+        }
+        let stats = &mut self.stats;
+        match result {
+            Ok(Some(())) => {
+                let state2 = &self.state2;
+                stats.record(state2.get_players_played_count() as usize, || {
+                    state2.schedule()
+                });
+                self.stack.push(state);
+                self.stack.push(self.state2.clone());
+                SearchStep::Searching
+            }
+            Ok(None) => {
+                stats.record(state.get_players_played_count() as usize, || {
+                    state.schedule()
+                });
+                stats.solutions += 1;
+                let schedule = state.schedule();
+                observer.solution_found(&schedule);
+                self.leave(&state, observer);
+                SearchStep::Found(schedule)
+            }
+            Err(_) => {
+                stats.record(state.get_players_played_count() as usize, || {
+                    state.schedule()
+                });
+                stats.backtracks += 1;
+                observer.dead_end(state.get_players_played_count() as usize);
+                self.leave(&state, observer);
+                SearchStep::Searching
+            }
+        }
+    }
+
+    /// Tells `observer` about going back from `state` to the next state to be stepped
+    fn leave<O: SearchObserver>(&self, state: &State<S>, observer: &mut O) {
+        if let Some(next) = self.stack.last() {
+            if observer.watches_players() {
+                state.for_each_player_not_in(next, |round, table, player| {
+                    observer.removed(round, table, player)
+                });
+            }
+            observer.backtracked(next.get_players_played_count() as usize);
+        }
+    }
+
+    pub fn config(&self) -> &Config {
+        self.state2.get_config()
+    }
+
+    /// Progress since the search was made or resumed
+    pub fn stats(&self) -> SearchStats {
+        SearchStats {
+            elapsed: self.started.elapsed(),
+            ..self.stats.clone()
+        }
+    }
+
+    /// Progress without the elapsed time, cheap enough to read every step
+    pub fn counts(&self) -> &SearchStats {
+        &self.stats
+    }
+
+    /// Steps until a schedule is found, the search finishes or a limit is reached. Limits count
+    /// from when the search was made or resumed
+    pub fn find(&mut self, limits: &SearchLimits) -> SearchOutcome {
+        match limits::find(self, limits) {
+            Ok(outcome) => outcome,
+            Err(never) => match never {},
+        }
+    }
+
+    pub(crate) fn fits_config(&self) -> bool {
+        self.state2.fits_config() && self.stack.iter().all(State::fits_config)
+    }
+}
+
+impl<S: PlayerSet> Stepwise for StateSolutions<S> {
+    type Error = std::convert::Infallible;
+
+    fn step(&mut self) -> Result<SearchStep, Self::Error> {
+        Ok(StateSolutions::step(self))
+    }
+
+    fn counts(&self) -> &SearchStats {
+        &self.stats
+    }
+
+    fn started(&self) -> Instant {
+        self.started
+    }
+}
+
+impl<S: PlayerSet> Iterator for StateSolutions<S> {
+    type Item = Schedule;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.step() {
+                SearchStep::Searching => {}
+                SearchStep::Found(schedule) => return Some(schedule),
+                SearchStep::Finished => return None,
+            }
+        }
+    }
+}
+
+/// Totals from running a search to the end
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SolutionCount {
+    /// Schedules the search yielded
+    pub solutions: u64,
+    /// Schedules left after merging isomorphic ones, when counting with a filter
+    pub unique: Option<u64>,
+    pub elapsed: Duration,
+}
+
+/// Counts the schedules, and with a filter the schedules that are not isomorphic to each other
+pub fn count_solutions<I>(solutions: I, mut filter: Option<IsomorphismFilter>) -> SolutionCount
+where
+    I: IntoIterator<Item = Schedule>,
+{
+    let start = Instant::now();
+    let mut count = 0;
+    let mut unique = 0;
+    for schedule in solutions {
+        count += 1;
+        if let Some(filter) = filter.as_mut() {
+            if filter.insert_schedule(schedule.rounds()) {
+                unique += 1;
+            }
+        }
+    }
+    SolutionCount {
+        solutions: count,
+        unique: filter.map(|_| unique),
+        elapsed: start.elapsed(),
+    }
+}
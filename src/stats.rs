@@ -0,0 +1,99 @@
+//! Progress of a search, kept by the solvers as they step
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Schedule;
+
+/// Counts kept while searching, which can be read at any point during the search
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchStats {
+    /// Steps taken
+    pub nodes: u64,
+    /// Players removed, or for `State` partial schedules abandoned at a dead end
+    pub backtracks: u64,
+    pub solutions: u64,
+    /// Steps counted by the number of players placed once taken
+    pub depth_histogram: Vec<u64>,
+    /// Most players placed at once, with the schedule they were placed in
+    pub best_players_placed: usize,
+    pub best_partial: Option<Schedule>,
+    /// Time spent searching, as of when the stats were read
+    pub elapsed: Duration,
+}
+
+impl SearchStats {
+    /// Steps per second
+    pub fn rate(&self) -> f64 {
+        self.nodes as f64 / self.elapsed.as_secs_f64().max(1e-3)
+    }
+
+    /// Deepest and shallowest number of players placed by any step
+    pub fn depth_range(&self) -> Option<(usize, usize)> {
+        let mut depths = self
+            .depth_histogram
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count != 0)
+            .map(|(depth, _)| depth);
+        let min = depths.next()?;
+        Some((min, depths.next_back().unwrap_or(min)))
+    }
+
+    /// Counts a step which left `depth` players placed, calling `schedule` for the partial
+    /// schedule only if it is the best so far
+    pub(crate) fn record<F>(&mut self, depth: usize, schedule: F)
+    where
+        F: FnOnce() -> Schedule,
+    {
+        self.nodes += 1;
+        if self.depth_histogram.len() <= depth {
+            self.depth_histogram.resize(depth + 1, 0);
+        }
+        self.depth_histogram[depth] += 1;
+        if depth > self.best_players_placed || self.best_partial.is_none() {
+            self.best_players_placed = depth;
+            self.best_partial = Some(schedule());
+        }
+    }
+
+    /// Adds the counts from another search, keeping the better partial schedule and the longer
+    /// time, as when searches run side by side
+    pub fn merge(&mut self, other: &SearchStats) {
+        self.nodes += other.nodes;
+        self.backtracks += other.backtracks;
+        self.solutions += other.solutions;
+        if self.depth_histogram.len() < other.depth_histogram.len() {
+            self.depth_histogram.resize(other.depth_histogram.len(), 0);
+        }
+        for (total, count) in self.depth_histogram.iter_mut().zip(&other.depth_histogram) {
+            *total += count;
+        }
+        if other.best_partial.is_some()
+            && (self.best_partial.is_none() || other.best_players_placed > self.best_players_placed)
+        {
+            self.best_players_placed = other.best_players_placed;
+            self.best_partial = other.best_partial.clone();
+        }
+        self.elapsed = self.elapsed.max(other.elapsed);
+    }
+}
+
+impl std::fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Steps: {}, rate: {:.0}/s, backtracks: {}, solutions: {}, best placed: {}",
+            self.nodes,
+            self.rate(),
+            self.backtracks,
+            self.solutions,
+            self.best_players_placed
+        )?;
+        if let Some((min, max)) = self.depth_range() {
+            write!(f, ", depth: {}-{}", min, max)?;
+        }
+        Ok(())
+    }
+}
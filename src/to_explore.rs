@@ -1,89 +1,96 @@
 use crate::*;
 
 use serde::{Deserialize, Serialize};
-use std::convert::TryInto;
 
-const TO_EXPLORE_SHIFT: usize = 3;
-
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
-pub struct ToExplore(u64);
+/// Set of (round, table) slots, one bit per slot. Up to 64 slots are kept inline, so copying a
+/// `State` does not allocate for them
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ToExplore {
+    Inline(u64),
+    Boxed(Box<[u64]>),
+}
 
 impl ToExplore {
-    pub const fn empty() -> Self {
-        Self(0)
-    }
-    pub fn filled() -> Self {
-        let mut to_explore = 0;
-        for round in ROUNDS {
-            for table in TABLES {
-                to_explore |= Self::encode(round, table);
+    pub fn filled(config: &Config) -> Self {
+        let slot_count = config.round_count() * config.table_count();
+        let mut to_explore = if slot_count <= 64 {
+            Self::Inline(0)
+        } else {
+            Self::Boxed(vec![0; slot_count.div_ceil(64)].into_boxed_slice())
+        };
+        for round in config.rounds() {
+            for table in config.tables() {
+                let slot = Self::encode(config, round, table);
+                to_explore.words_mut()[slot / 64] |= 1 << (slot % 64);
             }
         }
-        Self(to_explore)
+        to_explore
     }
 
-    const fn encode(round: Round, table: Table) -> u64 {
-        1 << (((round as usize) << TO_EXPLORE_SHIFT) + table as usize)
-    }
-
-    fn decode(trailing_zeros: u32) -> (Round, Table) {
-        let round = trailing_zeros >> TO_EXPLORE_SHIFT;
-        let table = trailing_zeros - (round << TO_EXPLORE_SHIFT);
-        let round = (round as usize).try_into().unwrap();
-        let table = (table as usize).try_into().unwrap();
-        (round, table)
+    fn words(&self) -> &[u64] {
+        match self {
+            Self::Inline(word) => std::slice::from_ref(word),
+            Self::Boxed(words) => words,
+        }
     }
 
-    pub fn pop(&mut self) -> Option<(Round, Table)> {
-        if self.0 == 0 {
-            None
-        } else {
-            let trailing_zeros = self.0.trailing_zeros();
-            self.0 &= !(1 << trailing_zeros);
-            let (round, table) = Self::decode(trailing_zeros);
-            if table as usize >= TABLE_COUNT || round as usize >= ROUND_COUNT {
-                unreachable!();
-            }
-            Some((round, table))
+    fn words_mut(&mut self) -> &mut [u64] {
+        match self {
+            Self::Inline(word) => std::slice::from_mut(word),
+            Self::Boxed(words) => words,
         }
     }
-    pub fn remove(&mut self, round: Round, table: Table) {
-        self.0 &= !Self::encode(round, table);
-    }
-    pub fn add(&mut self, round: Round, table: Table) {
-        self.0 |= Self::encode(round, table);
-    }
-}
 
-impl core::ops::BitAnd for ToExplore {
-    type Output = Self;
-    fn bitand(self, rhs: Self) -> Self::Output {
-        Self(self.0 & rhs.0)
+    /// Whether there is a bit for every slot and none beyond them
+    pub(crate) fn fits_config(&self, config: &Config) -> bool {
+        let slot_count = config.round_count() * config.table_count();
+        let fits_words = match self {
+            Self::Inline(_) => slot_count <= 64,
+            Self::Boxed(words) => slot_count > 64 && words.len() == slot_count.div_ceil(64),
+        };
+        fits_words
+            && self
+                .words()
+                .iter()
+                .enumerate()
+                .all(|(i, &word)| i * 64 + (64 - word.leading_zeros() as usize) <= slot_count)
     }
-}
 
-impl core::ops::BitAndAssign for ToExplore {
-    fn bitand_assign(&mut self, rhs: Self) {
-        self.0 &= rhs.0
+    fn encode(config: &Config, round: Round, table: Table) -> usize {
+        round.index() * config.table_count() + table.index()
     }
-}
 
-impl core::ops::BitOr for ToExplore {
-    type Output = Self;
-    fn bitor(self, rhs: Self) -> Self::Output {
-        Self(self.0 | rhs.0)
+    fn decode(config: &Config, slot: usize) -> (Round, Table) {
+        let round = config.round(slot / config.table_count());
+        let table = config.table(slot % config.table_count());
+        (round.unwrap(), table.unwrap())
     }
-}
 
-impl core::ops::BitOrAssign for ToExplore {
-    fn bitor_assign(&mut self, rhs: Self) {
-        self.0 |= rhs.0
+    /// The slot `pop` would take, leaving it in the set
+    pub fn first(&self, config: &Config) -> Option<(Round, Table)> {
+        let (i, word) = self
+            .words()
+            .iter()
+            .enumerate()
+            .find(|(_, word)| **word != 0)?;
+        Some(Self::decode(
+            config,
+            i * 64 + word.trailing_zeros() as usize,
+        ))
     }
-}
 
-impl core::ops::Not for ToExplore {
-    type Output = Self;
-    fn not(self) -> Self {
-        Self(!self.0)
+    pub fn pop(&mut self, config: &Config) -> Option<(Round, Table)> {
+        let (i, word) = self
+            .words_mut()
+            .iter_mut()
+            .enumerate()
+            .find(|(_, word)| **word != 0)?;
+        let trailing_zeros = word.trailing_zeros() as usize;
+        *word &= !(1 << trailing_zeros);
+        Some(Self::decode(config, i * 64 + trailing_zeros))
+    }
+    pub fn remove(&mut self, config: &Config, round: Round, table: Table) {
+        let slot = Self::encode(config, round, table);
+        self.words_mut()[slot / 64] &= !(1 << (slot % 64));
     }
 }